@@ -0,0 +1,105 @@
+use std::io;
+
+use crate::event::is_closed;
+use crate::{Bar, BarMode, Event, EventStream, EventType, Socket};
+
+/// What changed about a watched bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarChange {
+    /// A `barconfig_update` replaced the bar's config
+    Config,
+    /// The bar was shown or hidden, e.g. by holding its modifier
+    Visibility(bool),
+}
+
+/// Follows the config and visibility of one bar
+pub struct BarWatcher {
+    bar: Bar,
+    visible_by_modifier: bool,
+    events: Option<EventStream>,
+}
+
+impl BarWatcher {
+    /// Watch bar `id` on the default socket
+    pub fn new(id: &str) -> crate::Result<BarWatcher> {
+        BarWatcher::with_socket(Socket::new()?, id)
+    }
+
+    /// Watch bar `id`, using `socket` to fetch the config and then for events
+    pub fn with_socket(mut socket: Socket, id: &str) -> crate::Result<BarWatcher> {
+        let bar = socket.get_bar_config(id)?;
+        let events = socket.subscribe(&[EventType::BarconfigUpdate, EventType::BarStateUpdate])?;
+        Ok(BarWatcher { bar, visible_by_modifier: false, events: Some(events) })
+    }
+
+    /// A watcher that is only fed through `apply`
+    pub fn detached(bar: Bar) -> BarWatcher {
+        BarWatcher { bar, visible_by_modifier: false, events: None }
+    }
+
+    pub fn bar(&self) -> &Bar {
+        &self.bar
+    }
+
+    pub fn visible_by_modifier(&self) -> bool {
+        self.visible_by_modifier
+    }
+
+    /// Whether the bar is currently shown
+    pub fn visible(&self) -> bool {
+        match self.bar.mode {
+            BarMode::Dock | BarMode::Overlay => true,
+            BarMode::Hide => self.bar.hidden_state.as_deref() == Some("show") || self.visible_by_modifier,
+            BarMode::Invisible => false,
+        }
+    }
+
+    /// Update from an event, returning what changed for this bar
+    pub fn apply(&mut self, event: &Event) -> Option<BarChange> {
+        match event {
+            Event::BarconfigUpdate(bar) if bar.id == self.bar.id => {
+                if *bar == self.bar {
+                    return None;
+                }
+                self.bar = bar.clone();
+                Some(BarChange::Config)
+            }
+            Event::BarStateUpdate(state) if state.id == self.bar.id => {
+                let was = self.visible();
+                self.visible_by_modifier = state.visible_by_modifier;
+                let now = self.visible();
+                (was != now).then_some(BarChange::Visibility(now))
+            }
+            _ => None,
+        }
+    }
+
+    /// Block until the bar changes
+    pub fn next_change(&mut self) -> crate::Result<BarChange> {
+        loop {
+            let event = match &mut self.events {
+                Some(events) => events.next_event()?,
+                None => return Err(io::Error::new(io::ErrorKind::NotConnected, "watcher has no event stream").into()),
+            };
+            if let Some(change) = self.apply(&event) {
+                return Ok(change);
+            }
+        }
+    }
+}
+
+impl Iterator for BarWatcher {
+    type Item = crate::Result<BarChange>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.as_ref()?;
+        match self.next_change() {
+            // Stop rather than report the closed connection forever
+            Err(e) if is_closed(&e) => {
+                self.events = None;
+                None
+            }
+            result => Some(result),
+        }
+    }
+}
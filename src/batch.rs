@@ -0,0 +1,90 @@
+use std::fmt;
+
+use crate::parser::count_commands;
+use crate::{CommandResult, MessageType, Result, Socket};
+
+/// How `Socket::run_batch` handles failing commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchPolicy {
+    /// Send all commands in one message and report every failure
+    ReportAll,
+    /// Send commands one message at a time and stop at the first failure
+    StopOnFailure,
+}
+
+/// Results of one command passed to `Socket::run_batch`
+#[derive(Debug)]
+pub struct CommandOutcome<'a, C> {
+    pub command: &'a C,
+    /// One entry per sub-command; `None` if sway did not run it
+    pub results: Vec<Option<CommandResult>>,
+}
+
+impl<C> CommandOutcome<'_, C> {
+    /// Whether every sub-command ran and succeeded
+    pub fn succeeded(&self) -> bool {
+        self.results.iter().all(|res| res.as_ref().is_some_and(|res| res.success))
+    }
+
+    /// Error messages reported by sway for this command
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.results.iter().flatten().filter_map(|res| res.error.as_deref())
+    }
+}
+
+/// Distribute sway's flat result list over the commands that produced it
+///
+/// Sway replies with one result per sub-command and stops early on some
+/// errors, so trailing sub-commands may have no result.
+pub(crate) fn pair<'a, C>(
+    commands: &'a [C],
+    counts: &[usize],
+    results: Vec<CommandResult>,
+) -> Vec<CommandOutcome<'a, C>> {
+    let mut results = results.into_iter();
+    commands
+        .iter()
+        .zip(counts)
+        .map(|(command, &count)| CommandOutcome {
+            command,
+            results: (0..count).map(|_| results.next()).collect(),
+        })
+        .collect()
+}
+
+impl Socket {
+    /// Run several commands and pair sway's results with the originals
+    ///
+    /// Each command may hold criteria or `,`-chained sub-commands; commands
+    /// are separated with `;` so criteria never leak between them. Commands
+    /// are sent as written and failures are left to sway to report; they are
+    /// only split locally to tell how many results belong to each.
+    pub fn run_batch<'a, C: fmt::Display>(
+        &mut self,
+        commands: &'a [C],
+        policy: BatchPolicy,
+    ) -> Result<Vec<CommandOutcome<'a, C>>> {
+        let rendered: Vec<String> = commands.iter().map(|cmd| cmd.to_string()).collect();
+        let counts: Vec<usize> = rendered.iter().map(|cmd| count_commands(cmd)).collect();
+        match policy {
+            BatchPolicy::ReportAll => {
+                let results = self.mesg(MessageType::RunCommand, Some(&rendered.join("; ")))?;
+                Ok(pair(commands, &counts, results))
+            }
+            BatchPolicy::StopOnFailure => {
+                let mut outcomes = pair(commands, &counts, Vec::new());
+                for (cmd, outcome) in rendered.iter().zip(outcomes.iter_mut()) {
+                    let results: Vec<CommandResult> = self.mesg(MessageType::RunCommand, Some(cmd))?;
+                    let mut results = results.into_iter();
+                    for slot in outcome.results.iter_mut() {
+                        *slot = results.next();
+                    }
+                    if !outcome.succeeded() {
+                        break;
+                    }
+                }
+                Ok(outcomes)
+            }
+        }
+    }
+}
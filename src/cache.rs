@@ -0,0 +1,415 @@
+use crate::{
+    Event, EventStream, EventType, Feature, Input, InputChange, Node, NodeFullscreenMode, NodeLayout, NodeType,
+    Output, Socket, Version, WindowChange, Workspace, WorkspaceChange,
+};
+
+/// Local copy of compositor state, kept current by applying events
+///
+/// Events that carry enough information are applied in place. Anything that
+/// would need guessing, such as where a new window was inserted, marks the
+/// affected part stale and the next `next_event` call refetches just that:
+/// the tree for window and workspace changes, outputs for output changes and
+/// inputs for input changes.
+pub struct StateCache {
+    query: Option<Socket>,
+    events: Option<EventStream>,
+    version: Option<Version>,
+    tree: Node,
+    workspaces: Vec<Workspace>,
+    outputs: Vec<Output>,
+    marks: Vec<String>,
+    binding_mode: String,
+    inputs: Vec<Input>,
+    stale: Stale,
+    resyncs: usize,
+}
+
+/// Parts of the state that events could not be applied to
+#[derive(Debug, Clone, Copy, Default)]
+struct Stale {
+    tree: bool,
+    outputs: bool,
+    inputs: bool,
+}
+
+impl StateCache {
+    /// Subscribe on one connection, then snapshot over another
+    pub fn new() -> crate::Result<StateCache> {
+        let mut query = Socket::new()?;
+        let version = query.get_version()?;
+        let mut events = vec![EventType::Window, EventType::Workspace, EventType::Output, EventType::Mode];
+        if version.supports(Feature::Inputs) {
+            events.push(EventType::Input);
+        }
+        // Subscribing first means no event is lost between the two
+        let events = Socket::new()?.subscribe(&events)?;
+        let mut cache = StateCache::snapshot(&mut query, &version)?;
+        cache.query = Some(query);
+        cache.events = Some(events);
+        cache.version = Some(version);
+        Ok(cache)
+    }
+
+    /// A cache holding the given state, updated only through `apply`
+    pub fn from_parts(
+        tree: Node,
+        workspaces: Vec<Workspace>,
+        outputs: Vec<Output>,
+        binding_mode: String,
+        inputs: Vec<Input>,
+    ) -> StateCache {
+        let mut cache = StateCache {
+            query: None,
+            events: None,
+            version: None,
+            tree,
+            workspaces,
+            outputs,
+            marks: Vec::new(),
+            binding_mode,
+            inputs,
+            stale: Stale::default(),
+            resyncs: 0,
+        };
+        cache.collect_marks();
+        cache
+    }
+
+    fn snapshot(socket: &mut Socket, version: &Version) -> crate::Result<StateCache> {
+        let binding_mode = if version.supports(Feature::GetBindingState) {
+            socket.get_binding_state()?
+        } else {
+            "default".to_string()
+        };
+        let inputs = if version.supports(Feature::Inputs) { socket.get_inputs()? } else { Vec::new() };
+        Ok(StateCache::from_parts(
+            socket.get_tree()?,
+            socket.get_workspaces()?,
+            socket.get_outputs()?,
+            binding_mode,
+            inputs,
+        ))
+    }
+
+    pub fn tree(&self) -> &Node {
+        &self.tree
+    }
+    pub fn workspaces(&self) -> &[Workspace] {
+        &self.workspaces
+    }
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+    pub fn marks(&self) -> &[String] {
+        &self.marks
+    }
+    pub fn binding_mode(&self) -> &str {
+        &self.binding_mode
+    }
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    /// Whether an event could not be applied and state must be refetched
+    pub fn is_stale(&self) -> bool {
+        self.stale.tree || self.stale.outputs || self.stale.inputs
+    }
+
+    /// How many times state was refetched after the first snapshot
+    pub fn resyncs(&self) -> usize {
+        self.resyncs
+    }
+
+    /// Refetch all state
+    pub fn resync(&mut self) -> crate::Result<()> {
+        let (Some(query), Some(version)) = (&mut self.query, &self.version) else {
+            return Err(not_connected("cache has no socket"));
+        };
+        let fresh = StateCache::snapshot(query, version)?;
+        self.tree = fresh.tree;
+        self.workspaces = fresh.workspaces;
+        self.outputs = fresh.outputs;
+        self.marks = fresh.marks;
+        self.binding_mode = fresh.binding_mode;
+        self.inputs = fresh.inputs;
+        self.stale = Stale::default();
+        self.resyncs += 1;
+        Ok(())
+    }
+
+    /// Refetch only the parts marked stale
+    pub(crate) fn refresh(&mut self) -> crate::Result<()> {
+        let Some(query) = &mut self.query else {
+            return Err(not_connected("cache has no socket"));
+        };
+        if self.stale.outputs {
+            self.outputs = query.get_outputs()?;
+        }
+        if self.stale.inputs {
+            self.inputs = query.get_inputs()?;
+        }
+        if self.stale.tree {
+            self.tree = query.get_tree()?;
+            self.workspaces = workspaces(&self.tree);
+            self.collect_marks();
+        }
+        self.stale = Stale::default();
+        self.resyncs += 1;
+        Ok(())
+    }
+
+    /// Wait for the next event, apply it and refetch what it left stale
+    pub fn next_event(&mut self) -> crate::Result<Event> {
+        let event = self.read_event()?;
+        if self.is_stale() {
+            self.refresh()?;
+        }
+        Ok(event)
+    }
+
+    /// Wait for the next event and apply it, leaving stale parts for later
+    pub(crate) fn read_event(&mut self) -> crate::Result<Event> {
+        let event = match &mut self.events {
+            Some(events) => events.next_event()?,
+            None => return Err(not_connected("cache has no event stream")),
+        };
+        self.apply(&event);
+        Ok(event)
+    }
+
+    /// Make `next_event` fail with `WouldBlock` or `TimedOut` after `timeout`
+    pub fn set_read_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        match &self.events {
+            Some(events) => events.set_read_timeout(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply an event, returning false if it left the cache stale
+    pub fn apply(&mut self, event: &Event) -> bool {
+        let applied = match event {
+            Event::Window(e) => self.apply_window(e.change, &e.container),
+            Event::Workspace(e) => match (e.change, &e.current) {
+                (WorkspaceChange::Focus, Some(current)) => self.apply_workspace(current, true),
+                (WorkspaceChange::Urgent, Some(current)) => self.apply_workspace(current, false),
+                _ => false,
+            },
+            Event::Mode(e) => {
+                self.binding_mode = e.change.clone();
+                true
+            }
+            Event::Input(e) => {
+                let applied = self.apply_input(e.change, &e.input);
+                self.stale.inputs |= !applied;
+                return applied;
+            }
+            // Sway only reports that something about outputs changed, which
+            // can also move workspaces around
+            Event::Output(_) => {
+                self.stale.outputs = true;
+                false
+            }
+            _ => true,
+        };
+        self.stale.tree |= !applied;
+        applied
+    }
+
+    fn apply_window(&mut self, change: WindowChange, container: &Node) -> bool {
+        match change {
+            WindowChange::Title | WindowChange::Mark | WindowChange::Urgent => {
+                if !self.replace(container) {
+                    return false;
+                }
+            }
+            WindowChange::Focus => {
+                if !self.replace(container) {
+                    return false;
+                }
+                self.focus(container.id);
+                if !self.show_focused(container.id) {
+                    return false;
+                }
+            }
+            // These change where nodes are, or what else shows and at which
+            // size, which the event does not say
+            WindowChange::New
+            | WindowChange::Close
+            | WindowChange::Move
+            | WindowChange::Floating
+            | WindowChange::FullscreenMode => return false,
+        }
+        self.collect_marks();
+        true
+    }
+
+    fn apply_workspace(&mut self, current: &Node, focus: bool) -> bool {
+        let Some(index) = self.workspaces.iter().position(|ws| Some(&ws.name) == current.name.as_ref()) else {
+            return false;
+        };
+        if !self.replace(current) {
+            return false;
+        }
+        let focused = current.iter().find(|node| node.focused).map(|node| node.id);
+        if focus {
+            self.focus(focused.unwrap_or(current.id));
+            let output = self.workspaces[index].output.clone();
+            for (i, ws) in self.workspaces.iter_mut().enumerate() {
+                ws.focused = i == index;
+                if ws.output == output {
+                    ws.visible = i == index;
+                }
+            }
+        }
+        self.workspaces[index].urgent = current.urgent;
+        self.collect_marks();
+        true
+    }
+
+    fn apply_input(&mut self, change: InputChange, input: &Input) -> bool {
+        let index = self.inputs.iter().position(|i| i.identifier == input.identifier);
+        match (change, index) {
+            (InputChange::Added, None) => self.inputs.push(input.clone()),
+            (InputChange::Removed, Some(index)) => {
+                self.inputs.remove(index);
+            }
+            (InputChange::Removed, None) => {}
+            (_, Some(index)) => self.inputs[index] = input.clone(),
+            (_, None) => return false,
+        }
+        true
+    }
+
+    /// Swap in a fresh copy of a node that is already in the tree
+    fn replace(&mut self, node: &Node) -> bool {
+        match find_mut(&mut self.tree, node.id) {
+            Some(old) => {
+                *old = node.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move focus to `id`: set the focused flag and put it first in every
+    /// ancestor's focus list
+    fn focus(&mut self, id: u64) {
+        let Some(path) = path_to(&self.tree, id) else { return };
+        clear_focused(&mut self.tree);
+        let mut node = &mut self.tree;
+        for &child in &path[1..] {
+            node.focus.retain(|&f| f != child);
+            node.focus.insert(0, child);
+            let Some(next) = node.nodes.iter_mut().chain(node.floating_nodes.iter_mut()).find(|n| n.id == child)
+            else {
+                return;
+            };
+            node = next;
+        }
+        node.focused = true;
+    }
+
+    /// Update which windows show on the workspace holding the newly focused
+    /// `id`, as tabs and stacks bring their focused child to the front
+    ///
+    /// Returns false when the workspace was not already visible or has a
+    /// fullscreen window, which the tree alone cannot settle.
+    fn show_focused(&mut self, id: u64) -> bool {
+        let Some(path) = path_to(&self.tree, id) else { return false };
+        let Some(workspace) = path.iter().filter_map(|&id| self.tree.find(id)).find(|n| n.node_type == NodeType::Workspace)
+        else {
+            // Outside any workspace, such as in the scratchpad, nothing shows
+            return true;
+        };
+        let visible = self.workspaces.iter().any(|ws| Some(&ws.name) == workspace.name.as_ref() && ws.visible);
+        let fullscreen = workspace.iter().any(|node| {
+            matches!(node.fullscreen_mode, Some(NodeFullscreenMode::Full | NodeFullscreenMode::Global))
+        });
+        if !visible || fullscreen {
+            return false;
+        }
+        let id = workspace.id;
+        if let Some(workspace) = find_mut(&mut self.tree, id) {
+            show(workspace, true);
+        }
+        true
+    }
+
+    fn collect_marks(&mut self) {
+        self.marks = self.tree.iter().flat_map(|node| node.marks.iter().cloned()).collect();
+    }
+}
+
+fn find_mut(node: &mut Node, id: u64) -> Option<&mut Node> {
+    if node.id == id {
+        return Some(node);
+    }
+    node.nodes.iter_mut().chain(node.floating_nodes.iter_mut()).find_map(|child| find_mut(child, id))
+}
+
+/// Ids from the root down to `id`
+fn path_to(node: &Node, id: u64) -> Option<Vec<u64>> {
+    if node.id == id {
+        return Some(vec![id]);
+    }
+    node.children().find_map(|child| {
+        let mut path = path_to(child, id)?;
+        path.insert(0, node.id);
+        Some(path)
+    })
+}
+
+/// Set `visible` below `node`: only the front child of a tab or stack shows
+fn show(node: &mut Node, visible: bool) {
+    let stacked = matches!(node.layout, NodeLayout::Tabbed | NodeLayout::Stacked);
+    let front = node.focus.iter().copied().find(|&id| node.nodes.iter().any(|child| child.id == id));
+    for child in node.nodes.iter_mut() {
+        let shown = visible && (!stacked || Some(child.id) == front);
+        if child.visible.is_some() {
+            child.visible = Some(shown);
+        }
+        show(child, shown);
+    }
+    for child in node.floating_nodes.iter_mut() {
+        if child.visible.is_some() {
+            child.visible = Some(visible);
+        }
+        show(child, visible);
+    }
+}
+
+fn clear_focused(node: &mut Node) {
+    node.focused = false;
+    for child in node.nodes.iter_mut().chain(node.floating_nodes.iter_mut()) {
+        clear_focused(child);
+    }
+}
+
+fn not_connected(message: &str) -> crate::Error {
+    std::io::Error::new(std::io::ErrorKind::NotConnected, message.to_string()).into()
+}
+
+/// Workspace list as `get_workspaces` reports it, read off the tree
+pub(crate) fn workspaces(tree: &Node) -> Vec<Workspace> {
+    let focused = tree.focused_workspace().map(|ws| ws.id);
+    let mut workspaces = Vec::new();
+    for output in tree.outputs() {
+        let visible = output.focus.first().copied();
+        for ws in output.nodes.iter().filter(|node| node.node_type == NodeType::Workspace) {
+            let name = ws.name.clone().unwrap_or_default();
+            let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            workspaces.push(Workspace {
+                num: name[..digits].parse().unwrap_or(-1),
+                name,
+                visible: visible == Some(ws.id),
+                focused: focused == Some(ws.id),
+                urgent: ws.urgent,
+                output: output.name.clone().unwrap_or_default(),
+                rect: ws.rect.clone(),
+                #[cfg(feature = "extra_fields")]
+                extra: Default::default(),
+            });
+        }
+    }
+    workspaces
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use crate::{Binding, BindingFlags, BindingKind, ParsedConfig, Socket};
+
+/// Modifier names in display order, with the spellings sway accepts for each
+const MODIFIERS: &[(&str, &[&str])] = &[
+    ("Mod4", &["mod4", "logo", "super"]),
+    ("Ctrl", &["ctrl", "control"]),
+    ("Mod1", &["mod1", "alt"]),
+    ("Shift", &["shift"]),
+    ("Mod2", &["mod2"]),
+    ("Mod3", &["mod3"]),
+    ("Mod5", &["mod5"]),
+];
+
+/// A key combination with canonical modifier names and order
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct KeyCombo {
+    pub modifiers: Vec<String>,
+    /// Keysym, keycode or button; single letters lowercased
+    pub key: String,
+}
+
+impl KeyCombo {
+    /// Normalize a combination such as `Super+shift+Q`
+    pub fn parse(keys: &str) -> KeyCombo {
+        let mut parts: Vec<&str> = keys.split('+').collect();
+        // A trailing `+` means the key itself is `plus`
+        let key = if keys.ends_with("++") || keys == "+" {
+            parts.retain(|part| !part.is_empty());
+            "plus".to_string()
+        } else {
+            parts.pop().unwrap_or("").to_string()
+        };
+        let mut modifiers: Vec<String> = Vec::new();
+        for part in parts {
+            let name = MODIFIERS
+                .iter()
+                .find(|(_, aliases)| aliases.contains(&part.to_lowercase().as_str()))
+                .map_or(part, |(name, _)| name);
+            if !modifiers.iter().any(|m| m == name) {
+                modifiers.push(name.to_string());
+            }
+        }
+        modifiers.sort_by_key(|m| MODIFIERS.iter().position(|(name, _)| name == m).unwrap_or(MODIFIERS.len()));
+        let key = if key.len() == 1 { key.to_lowercase() } else { key };
+        KeyCombo { modifiers, key }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier)?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+impl BindingFlags {
+    /// Flags as written on a `bindsym` line
+    pub fn names(&self) -> Vec<String> {
+        let set = [
+            (self.release, "--release"),
+            (self.locked, "--locked"),
+            (self.whole_window, "--whole-window"),
+            (self.border, "--border"),
+            (self.exclude_titlebar, "--exclude-titlebar"),
+            (self.to_code, "--to-code"),
+            (self.no_warn, "--no-warn"),
+            (self.no_repeat, "--no-repeat"),
+            (self.inhibited, "--inhibited"),
+        ];
+        let mut names: Vec<String> = set.iter().filter(|(on, _)| *on).map(|(_, name)| name.to_string()).collect();
+        if let Some(device) = &self.input_device {
+            names.push(format!("--input-device={}", device));
+        }
+        names
+    }
+}
+
+/// One row of a cheat sheet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Keybinding {
+    pub keys: KeyCombo,
+    /// Whether `keys` names a keycode rather than a keysym
+    pub keycode: bool,
+    pub flags: Vec<String>,
+    pub command: String,
+}
+
+impl From<&Binding> for Keybinding {
+    fn from(binding: &Binding) -> Keybinding {
+        Keybinding {
+            keys: KeyCombo::parse(&binding.keys),
+            keycode: binding.kind == BindingKind::Code,
+            flags: binding.flags.names(),
+            command: binding.command.clone(),
+        }
+    }
+}
+
+/// Bindings of one mode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModeSheet {
+    pub name: String,
+    pub bindings: Vec<Keybinding>,
+}
+
+/// Every keybinding, grouped by binding mode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheatSheet {
+    pub modes: Vec<ModeSheet>,
+}
+
+impl CheatSheet {
+    /// Build a sheet with a section per entry of `modes`, as returned by
+    /// `get_binding_modes`, followed by any other mode the config defines
+    pub fn new(config: &ParsedConfig, modes: &[String]) -> CheatSheet {
+        let mut names: Vec<&str> = modes.iter().map(String::as_str).collect();
+        for mode in &config.modes {
+            if !names.contains(&mode.name.as_str()) {
+                names.push(&mode.name);
+            }
+        }
+        let modes = names
+            .into_iter()
+            .map(|name| ModeSheet {
+                name: name.to_string(),
+                bindings: config
+                    .mode(name)
+                    .map(|mode| mode.bindings.iter().map(Keybinding::from).collect())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        CheatSheet { modes }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("cheat sheet serializes")
+    }
+
+    /// A heading and table per mode
+    pub fn to_markdown(&self) -> String {
+        let cell = |s: &str| s.replace('|', "\\|");
+        let mut out = String::new();
+        for mode in &self.modes {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("## {}\n\n", mode.name));
+            out.push_str("| Keys | Flags | Command |\n| --- | --- | --- |\n");
+            for binding in &mode.bindings {
+                out.push_str(&format!(
+                    "| `{}` | {} | `{}` |\n",
+                    cell(&binding.keys.to_string()),
+                    cell(&binding.flags.join(" ")),
+                    cell(&binding.command),
+                ));
+            }
+        }
+        out
+    }
+
+    /// Aligned plain-text columns per mode
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        for mode in &self.modes {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", mode.name));
+            let rows: Vec<[String; 3]> = mode
+                .bindings
+                .iter()
+                .map(|b| [b.keys.to_string(), b.flags.join(" "), b.command.clone()])
+                .collect();
+            let width = |column: usize| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0);
+            let (keys, flags) = (width(0), width(1));
+            for [k, f, c] in &rows {
+                let line = if flags == 0 {
+                    format!("{:keys$}  {}", k, c)
+                } else {
+                    format!("{:keys$}  {:flags$}  {}", k, f, c)
+                };
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for CheatSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_table())
+    }
+}
+
+impl Socket {
+    /// Cheat sheet of the running config
+    pub fn get_cheat_sheet(&mut self) -> crate::Result<CheatSheet> {
+        let config = self.get_parsed_config()?;
+        let modes = self.get_binding_modes()?;
+        Ok(CheatSheet::new(&config, &modes))
+    }
+}
@@ -0,0 +1,493 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{quote, Criteria};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction { Left, Right, Up, Down }
+
+/// `enable`, `disable` or `toggle`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch { Enable, Disable, Toggle }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Focus {
+    /// Bare `focus`, selecting the container matched by criteria
+    Container,
+    Direction(Direction),
+    Next,
+    Prev,
+    NextSibling,
+    PrevSibling,
+    Parent,
+    Child,
+    Floating,
+    Tiling,
+    ModeToggle,
+    Output(OutputTarget),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputTarget {
+    Name(String),
+    Direction(Direction),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceTarget {
+    Name(String),
+    /// `number <n>`, matching workspaces whose name starts with `n`
+    Number(i64),
+    Next,
+    Prev,
+    NextOnOutput,
+    PrevOnOutput,
+    BackAndForth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Default,
+    Splith,
+    Splitv,
+    Stacking,
+    Tabbed,
+    ToggleSplit,
+    ToggleAll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split { Horizontal, Vertical, Toggle, None }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Border {
+    None,
+    Normal(Option<u32>),
+    Pixel(Option<u32>),
+    Csd,
+    Toggle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Move {
+    Direction(Direction, Option<u32>),
+    /// `move container to workspace`
+    Workspace(WorkspaceTarget),
+    /// `move container to output`
+    Output(OutputTarget),
+    /// `move container to mark`
+    Mark(String),
+    Scratchpad,
+    /// `move [absolute] position <x> <y>`
+    Position { absolute: bool, x: i64, y: i64 },
+    /// `move [absolute] position center`
+    Center { absolute: bool },
+    /// `move position cursor`
+    Cursor,
+    /// `move workspace to output`
+    WorkspaceToOutput(OutputTarget),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit { Px, Ppt }
+
+/// A length for `resize`, in pixels or percentage points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Amount {
+    pub value: i64,
+    pub unit: Option<Unit>,
+}
+
+impl Amount {
+    pub fn px(value: i64) -> Amount {
+        Amount { value, unit: Some(Unit::Px) }
+    }
+    pub fn ppt(value: i64) -> Amount {
+        Amount { value, unit: Some(Unit::Ppt) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension { Width, Height, Up, Down, Left, Right }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resize {
+    Grow(Dimension, Amount),
+    Shrink(Dimension, Amount),
+    Set { width: Option<Amount>, height: Option<Amount> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapsKind { Inner, Outer, Horizontal, Vertical, Top, Right, Bottom, Left }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapsScope { All, Current }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjust { Set, Plus, Minus, Toggle }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleInhibit { Focus, Fullscreen, Open, None, Visible }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwapTarget {
+    /// X11 window id
+    Id(u64),
+    ConId(u64),
+    Mark(String),
+}
+
+/// A single sway command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Border(Border),
+    /// Shell command line, passed to `sh -c`
+    Exec(String),
+    ExecAlways(String),
+    Exit,
+    Floating(Switch),
+    Focus(Focus),
+    Fullscreen { toggle: Switch, global: bool },
+    Gaps { kind: GapsKind, scope: GapsScope, adjust: Adjust, amount: u32 },
+    InhibitIdle(IdleInhibit),
+    /// `input <identifier> <args...>`
+    Input { identifier: String, args: Vec<String> },
+    Kill,
+    Layout(Layout),
+    /// `mark [--add] [--toggle] <mark>`
+    Mark { mark: String, add: bool, toggle: bool },
+    Mode(String),
+    Move(Move),
+    Nop(Option<String>),
+    /// `opacity [set|plus|minus] <value>`; sway rejects `Adjust::Toggle`
+    Opacity { adjust: Adjust, value: f64 },
+    /// `output <name> <args...>`
+    Output { name: String, args: Vec<String> },
+    Reload,
+    /// `rename workspace [<from>] to <to>`
+    Rename { from: Option<String>, to: String },
+    Resize(Resize),
+    ScratchpadShow,
+    /// `seat <name> <args...>`
+    Seat { name: String, args: Vec<String> },
+    Split(Split),
+    Sticky(Switch),
+    Swap(SwapTarget),
+    TitleFormat(String),
+    Unmark(Option<String>),
+    Urgent(Switch),
+    Workspace(WorkspaceTarget),
+    /// Any other sway command, kept as its name and arguments
+    Other { name: String, args: Vec<String> },
+}
+
+/// Commands sharing optional criteria, rendered as `[criteria] cmd, cmd`
+#[derive(Debug, Clone, Default)]
+pub struct Chain {
+    pub criteria: Option<Criteria>,
+    pub commands: Vec<Command>,
+}
+
+impl Command {
+    /// Run this command on the containers matching `criteria`
+    pub fn with_criteria(self, criteria: Criteria) -> Chain {
+        Chain { criteria: Some(criteria), commands: vec![self] }
+    }
+
+    /// Chain another command after this one
+    pub fn then(self, command: Command) -> Chain {
+        Chain { criteria: None, commands: vec![self, command] }
+    }
+}
+
+impl Chain {
+    /// Chain another command, sharing the criteria
+    pub fn then(mut self, command: Command) -> Chain {
+        self.commands.push(command);
+        self
+    }
+}
+
+impl From<Command> for Chain {
+    fn from(command: Command) -> Chain {
+        Chain { criteria: None, commands: vec![command] }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Left => "left",
+            Direction::Right => "right",
+            Direction::Up => "up",
+            Direction::Down => "down",
+        })
+    }
+}
+
+impl fmt::Display for Switch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Switch::Enable => "enable",
+            Switch::Disable => "disable",
+            Switch::Toggle => "toggle",
+        })
+    }
+}
+
+impl fmt::Display for OutputTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputTarget::Name(name) => f.write_str(&quote(name)),
+            OutputTarget::Direction(direction) => write!(f, "{}", direction),
+        }
+    }
+}
+
+impl fmt::Display for WorkspaceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkspaceTarget::Name(name) => f.write_str(&quote(name)),
+            WorkspaceTarget::Number(n) => write!(f, "number {}", n),
+            WorkspaceTarget::Next => f.write_str("next"),
+            WorkspaceTarget::Prev => f.write_str("prev"),
+            WorkspaceTarget::NextOnOutput => f.write_str("next_on_output"),
+            WorkspaceTarget::PrevOnOutput => f.write_str("prev_on_output"),
+            WorkspaceTarget::BackAndForth => f.write_str("back_and_forth"),
+        }
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+        match self.unit {
+            Some(Unit::Px) => f.write_str(" px"),
+            Some(Unit::Ppt) => f.write_str(" ppt"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dimension::Width => "width",
+            Dimension::Height => "height",
+            Dimension::Up => "up",
+            Dimension::Down => "down",
+            Dimension::Left => "left",
+            Dimension::Right => "right",
+        })
+    }
+}
+
+impl fmt::Display for Adjust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Adjust::Set => "set",
+            Adjust::Plus => "plus",
+            Adjust::Minus => "minus",
+            Adjust::Toggle => "toggle",
+        })
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = |f: &mut fmt::Formatter<'_>, args: &[String]| {
+            args.iter().try_for_each(|arg| write!(f, " {}", quote(arg)))
+        };
+        match self {
+            Command::Border(border) => match border {
+                Border::None => f.write_str("border none"),
+                Border::Normal(None) => f.write_str("border normal"),
+                Border::Normal(Some(n)) => write!(f, "border normal {}", n),
+                Border::Pixel(None) => f.write_str("border pixel"),
+                Border::Pixel(Some(n)) => write!(f, "border pixel {}", n),
+                Border::Csd => f.write_str("border csd"),
+                Border::Toggle => f.write_str("border toggle"),
+            },
+            Command::Exec(cmd) => write!(f, "exec {}", quote_exec(cmd)),
+            Command::ExecAlways(cmd) => write!(f, "exec_always {}", quote_exec(cmd)),
+            Command::Exit => f.write_str("exit"),
+            Command::Floating(toggle) => write!(f, "floating {}", toggle),
+            Command::Focus(focus) => match focus {
+                Focus::Container => f.write_str("focus"),
+                Focus::Direction(direction) => write!(f, "focus {}", direction),
+                Focus::Next => f.write_str("focus next"),
+                Focus::Prev => f.write_str("focus prev"),
+                Focus::NextSibling => f.write_str("focus next sibling"),
+                Focus::PrevSibling => f.write_str("focus prev sibling"),
+                Focus::Parent => f.write_str("focus parent"),
+                Focus::Child => f.write_str("focus child"),
+                Focus::Floating => f.write_str("focus floating"),
+                Focus::Tiling => f.write_str("focus tiling"),
+                Focus::ModeToggle => f.write_str("focus mode_toggle"),
+                Focus::Output(output) => write!(f, "focus output {}", output),
+            },
+            Command::Fullscreen { toggle, global } => {
+                write!(f, "fullscreen {}", toggle)?;
+                if *global {
+                    f.write_str(" global")?;
+                }
+                Ok(())
+            }
+            Command::Gaps { kind, scope, adjust, amount } => {
+                let kind = match kind {
+                    GapsKind::Inner => "inner",
+                    GapsKind::Outer => "outer",
+                    GapsKind::Horizontal => "horizontal",
+                    GapsKind::Vertical => "vertical",
+                    GapsKind::Top => "top",
+                    GapsKind::Right => "right",
+                    GapsKind::Bottom => "bottom",
+                    GapsKind::Left => "left",
+                };
+                let scope = match scope {
+                    GapsScope::All => "all",
+                    GapsScope::Current => "current",
+                };
+                write!(f, "gaps {} {} {} {}", kind, scope, adjust, amount)
+            }
+            Command::InhibitIdle(mode) => f.write_str(match mode {
+                IdleInhibit::Focus => "inhibit_idle focus",
+                IdleInhibit::Fullscreen => "inhibit_idle fullscreen",
+                IdleInhibit::Open => "inhibit_idle open",
+                IdleInhibit::None => "inhibit_idle none",
+                IdleInhibit::Visible => "inhibit_idle visible",
+            }),
+            Command::Input { identifier, args: rest } => {
+                write!(f, "input {}", quote(identifier))?;
+                args(f, rest)
+            }
+            Command::Kill => f.write_str("kill"),
+            Command::Layout(layout) => f.write_str(match layout {
+                Layout::Default => "layout default",
+                Layout::Splith => "layout splith",
+                Layout::Splitv => "layout splitv",
+                Layout::Stacking => "layout stacking",
+                Layout::Tabbed => "layout tabbed",
+                Layout::ToggleSplit => "layout toggle split",
+                Layout::ToggleAll => "layout toggle all",
+            }),
+            Command::Mark { mark, add, toggle } => {
+                f.write_str("mark")?;
+                if *add {
+                    f.write_str(" --add")?;
+                }
+                if *toggle {
+                    f.write_str(" --toggle")?;
+                }
+                write!(f, " {}", quote(mark))
+            }
+            Command::Mode(mode) => write!(f, "mode {}", quote(mode)),
+            Command::Move(target) => match target {
+                Move::Direction(direction, None) => write!(f, "move {}", direction),
+                Move::Direction(direction, Some(px)) => write!(f, "move {} {} px", direction, px),
+                Move::Workspace(workspace) => write!(f, "move container to workspace {}", workspace),
+                Move::Output(output) => write!(f, "move container to output {}", output),
+                Move::Mark(mark) => write!(f, "move container to mark {}", quote(mark)),
+                Move::Scratchpad => f.write_str("move scratchpad"),
+                Move::Position { absolute, x, y } => {
+                    let absolute = if *absolute { "absolute " } else { "" };
+                    write!(f, "move {}position {} px {} px", absolute, x, y)
+                }
+                Move::Center { absolute } => {
+                    let absolute = if *absolute { "absolute " } else { "" };
+                    write!(f, "move {}position center", absolute)
+                }
+                Move::Cursor => f.write_str("move position cursor"),
+                Move::WorkspaceToOutput(output) => write!(f, "move workspace to output {}", output),
+            },
+            Command::Nop(None) => f.write_str("nop"),
+            Command::Nop(Some(comment)) => write!(f, "nop {}", quote(comment)),
+            Command::Opacity { adjust: Adjust::Set, value } => write!(f, "opacity {}", value),
+            Command::Opacity { adjust, value } => write!(f, "opacity {} {}", adjust, value),
+            Command::Output { name, args: rest } => {
+                write!(f, "output {}", quote(name))?;
+                args(f, rest)
+            }
+            Command::Reload => f.write_str("reload"),
+            Command::Rename { from, to } => {
+                f.write_str("rename workspace")?;
+                if let Some(from) = from {
+                    write!(f, " {}", quote(from))?;
+                }
+                write!(f, " to {}", quote(to))
+            }
+            Command::Resize(resize) => match resize {
+                Resize::Grow(dimension, amount) => write!(f, "resize grow {} {}", dimension, amount),
+                Resize::Shrink(dimension, amount) => write!(f, "resize shrink {} {}", dimension, amount),
+                Resize::Set { width, height } => {
+                    f.write_str("resize set")?;
+                    if let Some(width) = width {
+                        write!(f, " width {}", width)?;
+                    }
+                    if let Some(height) = height {
+                        write!(f, " height {}", height)?;
+                    }
+                    Ok(())
+                }
+            },
+            Command::ScratchpadShow => f.write_str("scratchpad show"),
+            Command::Seat { name, args: rest } => {
+                write!(f, "seat {}", quote(name))?;
+                args(f, rest)
+            }
+            Command::Split(split) => f.write_str(match split {
+                Split::Horizontal => "split horizontal",
+                Split::Vertical => "split vertical",
+                Split::Toggle => "split toggle",
+                Split::None => "split none",
+            }),
+            Command::Sticky(toggle) => write!(f, "sticky {}", toggle),
+            Command::Swap(target) => match target {
+                SwapTarget::Id(id) => write!(f, "swap container with id {}", id),
+                SwapTarget::ConId(id) => write!(f, "swap container with con_id {}", id),
+                SwapTarget::Mark(mark) => write!(f, "swap container with mark {}", quote(mark)),
+            },
+            Command::TitleFormat(format) => write!(f, "title_format {}", quote(format)),
+            Command::Unmark(None) => f.write_str("unmark"),
+            Command::Unmark(Some(mark)) => write!(f, "unmark {}", quote(mark)),
+            Command::Urgent(toggle) => write!(f, "urgent {}", toggle),
+            Command::Workspace(workspace) => write!(f, "workspace {}", workspace),
+            Command::Other { name, args: rest } => {
+                f.write_str(name)?;
+                args(f, rest)
+            }
+        }
+    }
+}
+
+/// Quote an `exec` command line only if sway would split it
+///
+/// Sway hands the rest of the line to the shell verbatim, stripping quotes
+/// around all of it but unescaping nothing. So a line that would otherwise
+/// end at a `;` or `,`, or lose its own leading quote, is wrapped in a quote
+/// character it does not contain. A line that holds both kinds of quote
+/// cannot be expressed this way and is sent unchanged.
+fn quote_exec(cmd: &str) -> Cow<'_, str> {
+    if !cmd.contains([';', ',']) && !cmd.starts_with(['"', '\'']) {
+        return Cow::Borrowed(cmd);
+    }
+    match ['"', '\''].into_iter().find(|&q| !cmd.contains(q)) {
+        Some(q) => Cow::Owned(format!("{q}{cmd}{q}")),
+        None => Cow::Borrowed(cmd),
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(criteria) = &self.criteria {
+            write!(f, "{} ", criteria)?;
+        }
+        for (i, command) in self.commands.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", command)?;
+        }
+        Ok(())
+    }
+}
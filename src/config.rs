@@ -0,0 +1,762 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::parser::tokenize;
+use crate::{Criteria, OutputTarget, Socket, WorkspaceTarget};
+
+/// Where a config item was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// `None` for text that did not come from a file, such as `get_config`
+    /// without include resolution
+    pub file: Option<PathBuf>,
+    /// 1-based line number of the first line of the item
+    pub line: usize,
+}
+
+/// A config line, split into its name and unquoted arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub location: Location,
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// `set $name value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub location: Location,
+    /// Name including the leading `$`
+    pub name: String,
+    /// Value with earlier variables already expanded
+    pub value: String,
+}
+
+/// `include <pattern>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    pub location: Location,
+    pub pattern: String,
+    /// Files read for this include; empty when includes are not resolved
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind { Sym, Code }
+
+/// Flags accepted by `bindsym` and `bindcode`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindingFlags {
+    pub release: bool,
+    pub locked: bool,
+    pub whole_window: bool,
+    pub border: bool,
+    pub exclude_titlebar: bool,
+    pub to_code: bool,
+    pub no_warn: bool,
+    pub no_repeat: bool,
+    pub inhibited: bool,
+    pub input_device: Option<String>,
+}
+
+/// `bindsym` or `bindcode`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub location: Location,
+    pub kind: BindingKind,
+    pub flags: BindingFlags,
+    /// Key combination as written, e.g. `$mod+Shift+q` after expansion
+    pub keys: String,
+    /// Command text as written
+    pub command: String,
+}
+
+/// A binding mode with its bindings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mode {
+    /// `None` for the implicit `default` mode
+    pub location: Option<Location>,
+    pub name: String,
+    pub pango_markup: bool,
+    pub bindings: Vec<Binding>,
+}
+
+/// Settings for one `output`, `input` or `seat` identifier, merged across lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceConfig {
+    pub location: Location,
+    pub identifier: String,
+    /// One directive per setting, named after the setting
+    pub settings: Vec<Directive>,
+}
+
+/// A `bar { ... }` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarConfig {
+    pub location: Location,
+    /// From `bar <id> { }` or an `id` line inside the block
+    pub id: Option<String>,
+    pub settings: Vec<Directive>,
+    /// Lines of the nested `colors { }` block
+    pub colors: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssignTarget {
+    Workspace(WorkspaceTarget),
+    Output(OutputTarget),
+}
+
+/// `assign [criteria] [→] <target>`
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub location: Location,
+    pub criteria: Criteria,
+    pub target: AssignTarget,
+}
+
+/// `for_window [criteria] <command>`
+#[derive(Debug, Clone)]
+pub struct ForWindow {
+    pub location: Location,
+    pub criteria: Criteria,
+    pub command: String,
+}
+
+/// `exec` or `exec_always`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exec {
+    pub location: Location,
+    pub always: bool,
+    pub command: String,
+}
+
+/// A problem found while reading the config; parsing carries on past it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub location: Location,
+    pub message: String,
+}
+
+/// Structured view of a sway config
+#[derive(Debug, Clone)]
+pub struct ParsedConfig {
+    pub variables: Vec<Variable>,
+    pub includes: Vec<Include>,
+    /// Binding modes, starting with `default`
+    pub modes: Vec<Mode>,
+    pub outputs: Vec<DeviceConfig>,
+    pub inputs: Vec<DeviceConfig>,
+    pub seats: Vec<DeviceConfig>,
+    pub bars: Vec<BarConfig>,
+    pub assigns: Vec<Assign>,
+    pub for_windows: Vec<ForWindow>,
+    pub execs: Vec<Exec>,
+    /// Every other top-level line
+    pub directives: Vec<Directive>,
+    pub errors: Vec<ConfigError>,
+}
+
+/// A logical line or block before interpretation
+enum Item {
+    Line { line: usize, text: String },
+    Block { line: usize, header: String, body: Vec<Item> },
+}
+
+impl ParsedConfig {
+    /// Parse config text, recording `include` lines without reading them
+    pub fn parse(text: &str) -> ParsedConfig {
+        let mut reader = Reader::new(None);
+        reader.read(text, None);
+        reader.config
+    }
+
+    /// Parse config text read from `path`, resolving includes on disk
+    pub fn parse_with_includes(text: &str, path: &Path) -> ParsedConfig {
+        let mut reader = Reader::new(Some(path));
+        reader.read(text, Some(path));
+        reader.config
+    }
+
+    /// Read and parse the config file at `path`, resolving includes
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ParsedConfig> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Ok(ParsedConfig::parse_with_includes(&text, path))
+    }
+
+    /// Binding mode by name
+    pub fn mode(&self, name: &str) -> Option<&Mode> {
+        self.modes.iter().find(|mode| mode.name == name)
+    }
+
+    /// Value of a variable, given with its leading `$`
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.iter().rev().find(|var| var.name == name).map(|var| var.value.as_str())
+    }
+}
+
+impl Socket {
+    /// Fetch the running config and parse it, resolving includes relative
+    /// to the config file sway reports having loaded
+    pub fn get_parsed_config(&mut self) -> crate::Result<ParsedConfig> {
+        let text = self.get_config()?;
+        let path = self.get_version()?.loaded_config_file_name;
+        Ok(if path.is_empty() {
+            ParsedConfig::parse(&text)
+        } else {
+            ParsedConfig::parse_with_includes(&text, Path::new(&path))
+        })
+    }
+}
+
+struct Reader {
+    config: ParsedConfig,
+    resolve: bool,
+    seen: HashSet<PathBuf>,
+}
+
+impl Reader {
+    fn new(main: Option<&Path>) -> Reader {
+        let default = Mode { location: None, name: "default".to_string(), pango_markup: false, bindings: Vec::new() };
+        Reader {
+            config: ParsedConfig {
+                variables: Vec::new(),
+                includes: Vec::new(),
+                modes: vec![default],
+                outputs: Vec::new(),
+                inputs: Vec::new(),
+                seats: Vec::new(),
+                bars: Vec::new(),
+                assigns: Vec::new(),
+                for_windows: Vec::new(),
+                execs: Vec::new(),
+                directives: Vec::new(),
+                errors: Vec::new(),
+            },
+            resolve: main.is_some(),
+            seen: main.into_iter().map(|path| path.to_path_buf()).collect(),
+        }
+    }
+
+    fn read(&mut self, text: &str, file: Option<&Path>) {
+        let mut lines = logical_lines(text).into_iter().peekable();
+        let items = self.items(&mut lines, file, false);
+        for item in items {
+            self.interpret(item, file, "default");
+        }
+    }
+
+    fn location(file: Option<&Path>, line: usize) -> Location {
+        Location { file: file.map(Path::to_path_buf), line }
+    }
+
+    fn error(&mut self, location: Location, message: impl Into<String>) {
+        self.config.errors.push(ConfigError { location, message: message.into() });
+    }
+
+    /// Group logical lines into blocks
+    fn items(
+        &mut self,
+        lines: &mut std::iter::Peekable<std::vec::IntoIter<(usize, String)>>,
+        file: Option<&Path>,
+        nested: bool,
+    ) -> Vec<Item> {
+        let mut items = Vec::new();
+        while let Some((line, text)) = lines.next() {
+            if text == "}" {
+                if nested {
+                    return items;
+                }
+                self.error(Reader::location(file, line), "unmatched '}'");
+                continue;
+            }
+            let header = if let Some(header) = text.strip_suffix('{') {
+                header.trim_end().to_string()
+            } else if lines.peek().is_some_and(|(_, next)| next == "{") {
+                lines.next();
+                text
+            } else {
+                items.push(Item::Line { line, text });
+                continue;
+            };
+            let body = self.items(lines, file, true);
+            items.push(Item::Block { line, header, body });
+        }
+        if nested {
+            let line = items.last().map_or(0, |item| match item {
+                Item::Line { line, .. } | Item::Block { line, .. } => *line,
+            });
+            self.error(Reader::location(file, line), "unterminated block, expected '}'");
+        }
+        items
+    }
+
+    fn interpret(&mut self, item: Item, file: Option<&Path>, mode: &str) {
+        match item {
+            Item::Line { line, text } => self.line(&text, Reader::location(file, line), file, mode),
+            Item::Block { line, header, body } => {
+                let location = Reader::location(file, line);
+                let header = self.expand(&header);
+                let name = header.split_whitespace().next().unwrap_or("");
+                match name {
+                    "mode" => {
+                        let args = self.args(&header, &location);
+                        let pango_markup = args.iter().any(|arg| arg == "--pango_markup");
+                        let Some(name) = args.iter().skip(1).find(|arg| !arg.starts_with("--")) else {
+                            self.error(location, "mode block needs a name");
+                            return;
+                        };
+                        let name = name.clone();
+                        self.mode_entry(&name, pango_markup, &location);
+                        for item in body {
+                            self.interpret(item, file, &name);
+                        }
+                    }
+                    "bar" => self.bar(&header, body, location, file),
+                    _ => {
+                        for item in body {
+                            self.interpret(prefix(&header, item), file, mode);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn mode_entry(&mut self, name: &str, pango_markup: bool, location: &Location) -> &mut Mode {
+        let index = match self.config.modes.iter().position(|mode| mode.name == name) {
+            Some(index) => index,
+            None => {
+                self.config.modes.push(Mode {
+                    location: Some(location.clone()),
+                    name: name.to_string(),
+                    pango_markup,
+                    bindings: Vec::new(),
+                });
+                self.config.modes.len() - 1
+            }
+        };
+        &mut self.config.modes[index]
+    }
+
+    fn bar(&mut self, header: &str, body: Vec<Item>, location: Location, file: Option<&Path>) {
+        let mut bar = BarConfig {
+            id: self.args(header, &location).get(1).cloned(),
+            location,
+            settings: Vec::new(),
+            colors: Vec::new(),
+        };
+        for item in body {
+            match item {
+                Item::Line { line, text } => {
+                    let location = Reader::location(file, line);
+                    let text = self.expand(&text);
+                    if let Some(directive) = self.directive(&text, location) {
+                        if directive.name == "id" {
+                            bar.id = directive.args.first().cloned();
+                        }
+                        bar.settings.push(directive);
+                    }
+                }
+                Item::Block { header, body, .. } if header.trim() == "colors" => {
+                    for item in body {
+                        match item {
+                            Item::Line { line, text } => {
+                                let text = self.expand(&text);
+                                if let Some(directive) = self.directive(&text, Reader::location(file, line)) {
+                                    bar.colors.push(directive);
+                                }
+                            }
+                            Item::Block { line, .. } => {
+                                self.error(Reader::location(file, line), "unexpected block in bar colors");
+                            }
+                        }
+                    }
+                }
+                Item::Block { line, header, .. } => {
+                    self.error(Reader::location(file, line), format!("unexpected block '{}' in bar", header));
+                }
+            }
+        }
+        self.config.bars.push(bar);
+    }
+
+    /// Replace defined `$variables`, longest names first as sway does
+    fn expand(&self, text: &str) -> String {
+        if !text.contains('$') {
+            return text.to_string();
+        }
+        let mut names: Vec<&Variable> = self.config.variables.iter().collect();
+        names.sort_by_key(|var| std::cmp::Reverse(var.name.len()));
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find('$') {
+            out.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            match names.iter().find(|var| rest.starts_with(var.name.as_str())) {
+                Some(var) => {
+                    // Later definitions win
+                    let value = self.config.variables.iter().rev()
+                        .find(|v| v.name == var.name)
+                        .map_or("", |v| v.value.as_str());
+                    out.push_str(value);
+                    rest = &rest[var.name.len()..];
+                }
+                None => {
+                    out.push('$');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn args(&mut self, text: &str, location: &Location) -> Vec<String> {
+        match tokenize(text, 0) {
+            Ok(args) => args.into_iter().map(|arg| arg.text).collect(),
+            Err(e) => {
+                self.error(location.clone(), e.message);
+                Vec::new()
+            }
+        }
+    }
+
+    fn directive(&mut self, text: &str, location: Location) -> Option<Directive> {
+        let mut args = self.args(text, &location);
+        if args.is_empty() {
+            return None;
+        }
+        let name = args.remove(0);
+        Some(Directive { location, name, args })
+    }
+
+    fn line(&mut self, text: &str, location: Location, file: Option<&Path>, mode: &str) {
+        let name = text.split_whitespace().next().unwrap_or("");
+        if name == "set" {
+            let rest = text[3..].trim_start();
+            let (var, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if !var.starts_with('$') || var.len() < 2 {
+                self.error(location, "set needs a $variable name");
+                return;
+            }
+            let value = self.expand(value.trim());
+            self.config.variables.push(Variable { location, name: var.to_string(), value });
+            return;
+        }
+        let text = self.expand(text);
+        match name {
+            "include" => {
+                let pattern = text[7..].trim().to_string();
+                let pattern = self.args(&pattern, &location).join(" ");
+                let files = if self.resolve { self.resolve_include(&pattern, file, &location) } else { Vec::new() };
+                self.config.includes.push(Include { location, pattern, files: files.clone() });
+                for path in files {
+                    match fs::read_to_string(&path) {
+                        Ok(text) => self.read(&text, Some(&path)),
+                        Err(e) => {
+                            let location = Reader::location(Some(&path), 0);
+                            self.error(location, format!("cannot read include: {}", e));
+                        }
+                    }
+                }
+            }
+            "bindsym" | "bindcode" => self.binding(&text, location, mode),
+            "mode" => {
+                // Single-line form: mode <name> bindsym ...
+                let Ok(tokens) = tokenize(&text, 0) else {
+                    self.error(location, "unterminated quote");
+                    return;
+                };
+                let name_index = tokens.iter().skip(1).position(|arg| !arg.text.starts_with("--")).map(|i| i + 1);
+                match name_index.and_then(|i| tokens.get(i + 1).map(|next| (i, next))) {
+                    Some((i, next)) if next.text == "bindsym" || next.text == "bindcode" => {
+                        let mode = tokens[i].text.clone();
+                        self.mode_entry(&mode, false, &location);
+                        self.binding(&text[next.position..], location, &mode);
+                    }
+                    _ => {
+                        if let Some(directive) = self.directive(&text, location) {
+                            self.config.directives.push(directive);
+                        }
+                    }
+                }
+            }
+            "output" | "input" | "seat" => self.device(name, &text, location),
+            "assign" => self.assign(&text, location),
+            "for_window" => {
+                match criteria_and_rest(&text["for_window".len()..]) {
+                    Ok((criteria, command)) if !command.is_empty() => {
+                        self.config.for_windows.push(ForWindow { location, criteria, command });
+                    }
+                    Ok(_) => self.error(location, "for_window needs a command"),
+                    Err(message) => self.error(location, message),
+                }
+            }
+            "exec" | "exec_always" => {
+                let command = exec_command(&text[name.len()..]);
+                if command.is_empty() {
+                    self.error(location, format!("{} needs a command", name));
+                } else {
+                    self.config.execs.push(Exec { location, always: name == "exec_always", command });
+                }
+            }
+            _ => {
+                if let Some(directive) = self.directive(&text, location) {
+                    self.config.directives.push(directive);
+                }
+            }
+        }
+    }
+
+    fn binding(&mut self, text: &str, location: Location, mode: &str) {
+        let Ok(tokens) = tokenize(text, 0) else {
+            self.error(location, "unterminated quote");
+            return;
+        };
+        let kind = if tokens[0].text == "bindcode" { BindingKind::Code } else { BindingKind::Sym };
+        let mut flags = BindingFlags::default();
+        let mut index = 1;
+        while let Some(flag) = tokens.get(index).filter(|arg| arg.text.starts_with("--")) {
+            match flag.text.as_str() {
+                "--release" => flags.release = true,
+                "--locked" => flags.locked = true,
+                "--whole-window" => flags.whole_window = true,
+                "--border" => flags.border = true,
+                "--exclude-titlebar" => flags.exclude_titlebar = true,
+                "--to-code" => flags.to_code = true,
+                "--no-warn" => flags.no_warn = true,
+                "--no-repeat" => flags.no_repeat = true,
+                "--inhibited" => flags.inhibited = true,
+                other => match other.strip_prefix("--input-device=") {
+                    Some(device) => flags.input_device = Some(device.to_string()),
+                    None => self.error(location.clone(), format!("unknown binding flag '{}'", other)),
+                },
+            }
+            index += 1;
+        }
+        let (Some(keys), Some(command)) = (tokens.get(index), tokens.get(index + 1)) else {
+            self.error(location, "binding needs a key and a command");
+            return;
+        };
+        let binding = Binding {
+            location,
+            kind,
+            flags,
+            keys: keys.text.clone(),
+            command: text[command.position..].trim().to_string(),
+        };
+        self.mode_entry(mode, false, &binding.location.clone()).bindings.push(binding);
+    }
+
+    fn device(&mut self, kind: &str, text: &str, location: Location) {
+        let Some(directive) = self.directive(text, location) else { return };
+        let mut args = directive.args.into_iter();
+        let Some(identifier) = args.next() else {
+            self.error(directive.location, format!("{} needs an identifier", kind));
+            return;
+        };
+        let list = match kind {
+            "output" => &mut self.config.outputs,
+            "input" => &mut self.config.inputs,
+            _ => &mut self.config.seats,
+        };
+        let index = match list.iter().position(|device| device.identifier == identifier) {
+            Some(index) => index,
+            None => {
+                list.push(DeviceConfig { location: directive.location.clone(), identifier, settings: Vec::new() });
+                list.len() - 1
+            }
+        };
+        let mut args: Vec<String> = args.collect();
+        if !args.is_empty() {
+            let name = args.remove(0);
+            list[index].settings.push(Directive { location: directive.location, name, args });
+        }
+    }
+
+    fn assign(&mut self, text: &str, location: Location) {
+        let (criteria, rest) = match criteria_and_rest(&text["assign".len()..]) {
+            Ok(parsed) => parsed,
+            Err(message) => return self.error(location, message),
+        };
+        let mut args = self.args(&rest, &location);
+        if args.first().is_some_and(|arg| arg == "→") {
+            args.remove(0);
+        }
+        let target = match args.first().map(String::as_str) {
+            Some("output") if args.len() == 2 => {
+                let name = &args[1];
+                AssignTarget::Output(match name.as_str() {
+                    "left" => OutputTarget::Direction(crate::Direction::Left),
+                    "right" => OutputTarget::Direction(crate::Direction::Right),
+                    "up" => OutputTarget::Direction(crate::Direction::Up),
+                    "down" => OutputTarget::Direction(crate::Direction::Down),
+                    _ => OutputTarget::Name(name.clone()),
+                })
+            }
+            Some("workspace") if args.get(1).is_some_and(|arg| arg == "number") => {
+                match args.get(2).and_then(|n| n.parse().ok()) {
+                    Some(n) => AssignTarget::Workspace(WorkspaceTarget::Number(n)),
+                    None => return self.error(location, "assign needs a workspace number"),
+                }
+            }
+            Some("workspace") if args.len() > 1 => {
+                AssignTarget::Workspace(WorkspaceTarget::Name(args[1..].join(" ")))
+            }
+            Some(name) if name != "workspace" && name != "output" => {
+                AssignTarget::Workspace(WorkspaceTarget::Name(args.join(" ")))
+            }
+            _ => return self.error(location, "assign needs a workspace or output"),
+        };
+        self.config.assigns.push(Assign { location, criteria, target });
+    }
+
+    /// Paths matching an include pattern, skipping files already read
+    fn resolve_include(&mut self, pattern: &str, file: Option<&Path>, location: &Location) -> Vec<PathBuf> {
+        // Sway runs include paths through wordexp after substituting its own
+        // variables, so `~` and environment variables are expanded too
+        let text = expand_env(pattern, |name| env::var(name).ok());
+        let expanded = match text.strip_prefix("~/") {
+            Some(rest) => match env::var_os("HOME") {
+                Some(home) => Path::new(&home).join(rest),
+                None => PathBuf::from(&text),
+            },
+            None => PathBuf::from(&text),
+        };
+        let path = match file.and_then(Path::parent) {
+            Some(dir) if expanded.is_relative() => dir.join(expanded),
+            _ => expanded,
+        };
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut paths = if file_name.contains(['*', '?']) {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let mut matches: Vec<PathBuf> = fs::read_dir(dir)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| wildcard(&file_name, &entry.file_name().to_string_lossy()))
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_file())
+                        .collect()
+                })
+                .unwrap_or_default();
+            matches.sort();
+            matches
+        } else if path.is_file() {
+            vec![path]
+        } else {
+            self.error(location.clone(), format!("include '{}' not found", pattern));
+            Vec::new()
+        };
+        paths.retain(|path| self.seen.insert(path.clone()));
+        paths
+    }
+}
+
+/// Prefix each line of a generic block with its header
+fn prefix(header: &str, item: Item) -> Item {
+    match item {
+        Item::Line { line, text } => Item::Line { line, text: format!("{} {}", header, text) },
+        Item::Block { line, header: inner, body } => {
+            Item::Block { line, header: format!("{} {}", header, inner), body }
+        }
+    }
+}
+
+/// Join continued lines, drop comments and blank lines, and trim
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (index, raw) in text.lines().enumerate() {
+        let trimmed = raw.trim();
+        if pending.is_none() && (trimmed.is_empty() || trimmed.starts_with('#')) {
+            continue;
+        }
+        let (start, mut joined) = pending.take().unwrap_or((index + 1, String::new()));
+        match trimmed.strip_suffix('\\') {
+            Some(head) => {
+                joined.push_str(head);
+                pending = Some((start, joined));
+            }
+            None => {
+                joined.push_str(trimmed);
+                lines.push((start, joined));
+            }
+        }
+    }
+    if let Some(line) = pending {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Split leading criteria from the rest of a line
+fn criteria_and_rest(text: &str) -> std::result::Result<(Criteria, String), String> {
+    let (criteria, end) = Criteria::parse_prefix(text).map_err(|e| e.to_string())?;
+    Ok((criteria, text[end..].trim().to_string()))
+}
+
+/// Command line of an `exec`, without a single pair of surrounding quotes
+fn exec_command(rest: &str) -> String {
+    let rest = rest.trim();
+    match tokenize(rest, 0) {
+        Ok(tokens) if tokens.len() == 1 && tokens[0].quoted => tokens[0].text.clone(),
+        _ => rest.to_string(),
+    }
+}
+
+/// Replace `$NAME` and `${NAME}` with values from `lookup`, unset ones
+/// with nothing, as the shell does
+pub(crate) fn expand_env(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let (name, after) = match rest.strip_prefix('{').and_then(|inner| inner.split_once('}')) {
+            Some((name, after)) => (name, after),
+            None => {
+                let end = rest.find(|c| !is_name(c)).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() || !name.chars().all(is_name) {
+            out.push('$');
+            continue;
+        }
+        out.push_str(&lookup(name).unwrap_or_default());
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Match `name` against a pattern with `*` and `?` wildcards
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,452 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::{escape_regex, Node, NodeFloating, NodeType};
+
+/// Value sway compares a string property against
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `__focused__`: equal to the property of the focused container
+    Focused,
+    Regex(Regex),
+}
+
+/// Value for `con_id`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConId {
+    /// `__focused__`: the focused container
+    Focused,
+    Id(u64),
+}
+
+/// Value for `urgent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    /// `first`, `oldest`
+    Oldest,
+    /// `latest`, `newest`, `last`, `recent`
+    Latest,
+}
+
+/// A single criteria token
+#[derive(Debug, Clone)]
+pub enum Criterion {
+    All,
+    AppId(Pattern),
+    Class(Pattern),
+    Instance(Pattern),
+    Title(Pattern),
+    WindowRole(Pattern),
+    Shell(Pattern),
+    Workspace(Pattern),
+    ConMark(Regex),
+    ConId(ConId),
+    /// X11 window id
+    Id(u64),
+    Pid(u64),
+    WindowType(String),
+    Floating,
+    Tiling,
+    Urgent(Urgency),
+}
+
+/// Parsed criteria such as `[app_id="firefox" title="^Meet" floating]`
+#[derive(Debug, Clone, Default)]
+pub struct Criteria(pub Vec<Criterion>);
+
+/// Error from parsing criteria, with the byte offset it occurred at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriteriaError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CriteriaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for CriteriaError {}
+
+impl Criteria {
+    /// Parse criteria including the surrounding brackets
+    pub fn parse(s: &str) -> std::result::Result<Criteria, CriteriaError> {
+        let (criteria, end) = Criteria::parse_prefix(s)?;
+        match s[end..].find(|c: char| !c.is_whitespace()) {
+            Some(offset) => Err(error(end + offset, "unexpected text after criteria")),
+            None => Ok(criteria),
+        }
+    }
+
+    /// Parse criteria at the start of `s`, returning them with the offset just past `]`
+    pub fn parse_prefix(s: &str) -> std::result::Result<(Criteria, usize), CriteriaError> {
+        let mut chars = s.char_indices().peekable();
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some((_, '[')) => {}
+            Some((pos, _)) => return Err(error(pos, "expected '['")),
+            None => return Err(error(s.len(), "expected '['")),
+        }
+        let mut criteria = Vec::new();
+        loop {
+            skip_whitespace(&mut chars);
+            let start = match chars.peek() {
+                Some(&(pos, ']')) => {
+                    chars.next();
+                    return Ok((Criteria(criteria), pos + 1));
+                }
+                Some(&(pos, _)) => pos,
+                None => return Err(error(s.len(), "unterminated criteria, expected ']'")),
+            };
+            let mut end = start;
+            while let Some(&(pos, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = pos + c.len_utf8();
+                chars.next();
+            }
+            if end == start {
+                return Err(error(start, "expected a criteria name"));
+            }
+            let key = &s[start..end];
+            let value = match chars.peek() {
+                Some(&(_, '=')) => {
+                    chars.next();
+                    let value_start = chars.peek().map_or(s.len(), |&(pos, _)| pos);
+                    Some((value_start, read_value(s, &mut chars)?))
+                }
+                _ => None,
+            };
+            criteria.push(criterion(start, key, value)?);
+        }
+    }
+
+    /// Whether any token requires a value from the focused container
+    fn uses_focus(&self) -> bool {
+        self.0.iter().any(|criterion| matches!(
+            criterion,
+            Criterion::AppId(Pattern::Focused)
+                | Criterion::Class(Pattern::Focused)
+                | Criterion::Instance(Pattern::Focused)
+                | Criterion::Title(Pattern::Focused)
+                | Criterion::WindowRole(Pattern::Focused)
+                | Criterion::Shell(Pattern::Focused)
+                | Criterion::Workspace(Pattern::Focused)
+                | Criterion::ConId(ConId::Focused)
+        ))
+    }
+
+    /// The tokens that a container answers on its own, without its place
+    /// in the tree or the focus
+    pub(crate) fn local(&self) -> Criteria {
+        Criteria(self.0.iter().filter(|criterion| !criterion.needs_tree()).cloned().collect())
+    }
+
+    /// Whether every token also applies to containers without a window
+    fn matches_containers(&self) -> bool {
+        !self.0.is_empty()
+            && self.0.iter().all(|c| matches!(c, Criterion::ConId(_) | Criterion::ConMark(_)))
+    }
+}
+
+impl Criterion {
+    fn needs_tree(&self) -> bool {
+        matches!(
+            self,
+            Criterion::Workspace(_)
+                | Criterion::Floating
+                | Criterion::Tiling
+                | Criterion::Urgent(_)
+                | Criterion::ConId(ConId::Focused)
+                | Criterion::AppId(Pattern::Focused)
+                | Criterion::Class(Pattern::Focused)
+                | Criterion::Instance(Pattern::Focused)
+                | Criterion::Title(Pattern::Focused)
+                | Criterion::WindowRole(Pattern::Focused)
+                | Criterion::Shell(Pattern::Focused)
+        )
+    }
+}
+
+impl Pattern {
+    /// Pattern matching exactly `value`
+    pub fn literal(value: &str) -> Pattern {
+        let regex = format!("^{}$", escape_regex(value));
+        Pattern::Regex(Regex::new(&regex).expect("escaped regex is valid"))
+    }
+}
+
+impl FromStr for Criteria {
+    type Err = CriteriaError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Criteria::parse(s)
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::CharIndices<'a>>;
+
+fn error(position: usize, message: &str) -> CriteriaError {
+    CriteriaError { position, message: message.to_string() }
+}
+
+fn skip_whitespace(chars: &mut Chars<'_>) {
+    while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Read a quoted or bare value
+///
+/// Like sway, only the quote character itself is unescaped; other
+/// backslashes are passed through to the regex.
+fn read_value(s: &str, chars: &mut Chars<'_>) -> std::result::Result<String, CriteriaError> {
+    let mut value = String::new();
+    match chars.peek() {
+        Some(&(start, quote @ ('"' | '\''))) => {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) if c == quote => value.push(c),
+                        Some((_, c)) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => break,
+                    },
+                    Some((_, c)) if c == quote => return Ok(value),
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                }
+            }
+            Err(error(start, "unterminated quoted value"))
+        }
+        _ => {
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_whitespace() || c == ']' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            if value.is_empty() {
+                Err(error(chars.peek().map_or(s.len(), |&(pos, _)| pos), "expected a value"))
+            } else {
+                Ok(value)
+            }
+        }
+    }
+}
+
+fn criterion(
+    position: usize,
+    key: &str,
+    value: Option<(usize, String)>,
+) -> std::result::Result<Criterion, CriteriaError> {
+    let no_value = |criterion: Criterion| match value {
+        Some((pos, _)) => Err(error(pos, &format!("'{}' takes no value", key))),
+        None => Ok(criterion),
+    };
+    match key {
+        "all" => return no_value(Criterion::All),
+        "floating" => return no_value(Criterion::Floating),
+        "tiling" => return no_value(Criterion::Tiling),
+        _ => {}
+    }
+    let (pos, value) = value.ok_or_else(|| error(position, &format!("'{}' requires a value", key)))?;
+    let regex = |value: &str| {
+        Regex::new(value).map_err(|e| error(pos, &format!("invalid regex: {}", e)))
+    };
+    let pattern = |value: &str| match value {
+        "__focused__" => Ok(Pattern::Focused),
+        _ => regex(value).map(Pattern::Regex),
+    };
+    let number = |value: &str| {
+        value.parse::<u64>().map_err(|_| error(pos, &format!("'{}' requires a number", key)))
+    };
+    Ok(match key {
+        "app_id" => Criterion::AppId(pattern(&value)?),
+        "class" => Criterion::Class(pattern(&value)?),
+        "instance" => Criterion::Instance(pattern(&value)?),
+        "title" => Criterion::Title(pattern(&value)?),
+        "window_role" => Criterion::WindowRole(pattern(&value)?),
+        "shell" => Criterion::Shell(pattern(&value)?),
+        "workspace" => Criterion::Workspace(pattern(&value)?),
+        "con_mark" => Criterion::ConMark(regex(&value)?),
+        "con_id" => Criterion::ConId(match value.as_str() {
+            "__focused__" => ConId::Focused,
+            _ => ConId::Id(number(&value)?),
+        }),
+        "id" => Criterion::Id(number(&value)?),
+        "pid" => Criterion::Pid(number(&value)?),
+        "window_type" => Criterion::WindowType(value),
+        "urgent" => Criterion::Urgent(match value.as_str() {
+            "first" | "oldest" => Urgency::Oldest,
+            "latest" | "newest" | "last" | "recent" => Urgency::Latest,
+            _ => return Err(error(pos, "urgent must be first, oldest, latest, newest, last or recent")),
+        }),
+        _ => return Err(error(position, &format!("unknown criteria '{}'", key))),
+    })
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Focused => write!(f, "__focused__"),
+            Pattern::Regex(regex) => write_quoted(f, regex.as_str()),
+        }
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        if c == '"' {
+            write!(f, "\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Criterion::All => write!(f, "all"),
+            Criterion::AppId(p) => write!(f, "app_id={}", p),
+            Criterion::Class(p) => write!(f, "class={}", p),
+            Criterion::Instance(p) => write!(f, "instance={}", p),
+            Criterion::Title(p) => write!(f, "title={}", p),
+            Criterion::WindowRole(p) => write!(f, "window_role={}", p),
+            Criterion::Shell(p) => write!(f, "shell={}", p),
+            Criterion::Workspace(p) => write!(f, "workspace={}", p),
+            Criterion::ConMark(regex) => {
+                write!(f, "con_mark=")?;
+                write_quoted(f, regex.as_str())
+            }
+            Criterion::ConId(ConId::Focused) => write!(f, "con_id=__focused__"),
+            Criterion::ConId(ConId::Id(id)) => write!(f, "con_id={}", id),
+            Criterion::Id(id) => write!(f, "id={}", id),
+            Criterion::Pid(pid) => write!(f, "pid={}", pid),
+            Criterion::WindowType(t) => {
+                write!(f, "window_type=")?;
+                write_quoted(f, t)
+            }
+            Criterion::Floating => write!(f, "floating"),
+            Criterion::Tiling => write!(f, "tiling"),
+            Criterion::Urgent(Urgency::Oldest) => write!(f, "urgent=oldest"),
+            Criterion::Urgent(Urgency::Latest) => write!(f, "urgent=latest"),
+        }
+    }
+}
+
+impl fmt::Display for Criteria {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, criterion) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", criterion)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// What a candidate is compared against besides its own fields
+struct Context<'a> {
+    workspace: Option<&'a Node>,
+    floating: bool,
+    focused: Option<&'a Node>,
+    focused_workspace: Option<&'a Node>,
+}
+
+impl Node {
+    /// Whether this container is floating
+    pub fn is_floating(&self) -> bool {
+        self.node_type == NodeType::FloatingCon
+            || matches!(self.floating, Some(NodeFloating::AutoOn | NodeFloating::UserOn))
+    }
+
+    fn window_property(&self, get: fn(&crate::NodeWindowProperties) -> Option<&str>) -> Option<&str> {
+        self.window_properties.as_ref().and_then(get)
+    }
+
+    /// Containers below this node that `criteria` selects
+    ///
+    /// `__focused__` resolves against the focus chain of this node. Sway picks
+    /// `urgent=latest` by urgency time, which IPC does not expose, so the last
+    /// urgent match in tree order is used instead.
+    pub fn matching(&self, criteria: &Criteria) -> Vec<&Node> {
+        let focused = self.focused();
+        let focused_workspace = if criteria.uses_focus() { self.focused_workspace() } else { None };
+        let containers = criteria.matches_containers();
+        let mut matches: Vec<&Node> = self
+            .iter_with_parents()
+            .filter(|(node, _)| {
+                node.is_window()
+                    || (containers && matches!(node.node_type, NodeType::Con | NodeType::FloatingCon))
+            })
+            .filter(|(node, parents)| {
+                let workspace = parents.iter().rev().copied()
+                    .find(|parent| parent.node_type == NodeType::Workspace);
+                let floating = node.is_floating() || parents.iter().any(|parent| parent.is_floating());
+                let context = Context { workspace, floating, focused, focused_workspace };
+                criteria.0.iter().all(|criterion| node.satisfies(criterion, &context))
+            })
+            .map(|(node, _)| node)
+            .collect();
+        for criterion in criteria.0.iter() {
+            if let Criterion::Urgent(urgency) = criterion {
+                let pick = match urgency {
+                    Urgency::Oldest => matches.first().copied(),
+                    Urgency::Latest => matches.last().copied(),
+                };
+                matches = pick.into_iter().collect();
+            }
+        }
+        matches
+    }
+
+    fn satisfies(&self, criterion: &Criterion, context: &Context<'_>) -> bool {
+        let text = |pattern: &Pattern, get: &dyn Fn(&Node) -> Option<&str>| match pattern {
+            Pattern::Focused => match (context.focused.and_then(get), get(self)) {
+                (Some(focused), Some(value)) => focused == value,
+                _ => false,
+            },
+            Pattern::Regex(regex) => get(self).is_some_and(|value| regex.is_match(value)),
+        };
+        match criterion {
+            Criterion::All => true,
+            Criterion::AppId(p) => text(p, &|n| n.app_id.as_deref()),
+            Criterion::Class(p) => text(p, &|n| n.window_property(|w| Some(w.class.as_str()))),
+            Criterion::Instance(p) => text(p, &|n| n.window_property(|w| Some(w.instance.as_str()))),
+            Criterion::Title(p) => text(p, &|n| n.name.as_deref()),
+            Criterion::WindowRole(p) => text(p, &|n| n.window_property(|w| w.window_role.as_deref())),
+            Criterion::Shell(p) => text(p, &|n| n.shell.as_deref()),
+            Criterion::Workspace(Pattern::Focused) => {
+                match (context.workspace, context.focused_workspace) {
+                    (Some(workspace), Some(focused)) => workspace.id == focused.id,
+                    _ => false,
+                }
+            }
+            Criterion::Workspace(Pattern::Regex(regex)) => context.workspace
+                .and_then(|workspace| workspace.name.as_deref())
+                .is_some_and(|name| regex.is_match(name)),
+            Criterion::ConMark(regex) => self.marks.iter().any(|mark| regex.is_match(mark)),
+            Criterion::ConId(ConId::Focused) => context.focused.is_some_and(|f| f.id == self.id),
+            Criterion::ConId(ConId::Id(id)) => self.id == *id,
+            Criterion::Id(id) => self.window == Some(*id),
+            Criterion::Pid(pid) => self.pid == Some(*pid),
+            Criterion::WindowType(t) => self.window_type.as_deref() == Some(t.as_str()),
+            Criterion::Floating => context.floating,
+            Criterion::Tiling => !context.floating,
+            Criterion::Urgent(_) => self.urgent,
+        }
+    }
+}
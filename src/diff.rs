@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Node, NodeFullscreenMode, NodeLayout, Rectangle, Tree};
+
+/// One difference between two trees, keyed by container id
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added { id: u64, parent: Option<u64> },
+    Removed { id: u64 },
+    /// Reparented; `from` and `to` are parent ids
+    Moved { id: u64, from: Option<u64>, to: Option<u64> },
+    /// Ended up on another workspace, by workspace name
+    MovedWorkspace { id: u64, from: Option<String>, to: Option<String> },
+    Layout { id: u64, from: NodeLayout, to: NodeLayout },
+    Focused { id: u64, focused: bool },
+    Fullscreen { id: u64, from: Option<NodeFullscreenMode>, to: Option<NodeFullscreenMode> },
+    Floating { id: u64, floating: bool },
+    Geometry { id: u64, from: Rectangle, to: Rectangle },
+    Title { id: u64, from: Option<String>, to: Option<String> },
+}
+
+impl Change {
+    pub fn id(&self) -> u64 {
+        match *self {
+            Change::Added { id, .. }
+            | Change::Removed { id }
+            | Change::Moved { id, .. }
+            | Change::MovedWorkspace { id, .. }
+            | Change::Layout { id, .. }
+            | Change::Focused { id, .. }
+            | Change::Fullscreen { id, .. }
+            | Change::Floating { id, .. }
+            | Change::Geometry { id, .. }
+            | Change::Title { id, .. } => id,
+        }
+    }
+}
+
+/// Changes from one tree snapshot to another
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeDiff {
+    pub changes: Vec<Change>,
+    /// Names of every container seen, for rendering
+    labels: HashMap<u64, String>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Changes concerning container `id`
+    pub fn for_id(&self, id: u64) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(move |change| change.id() == id)
+    }
+
+    fn label(&self, id: u64) -> &str {
+        self.labels.get(&id).map_or("", String::as_str)
+    }
+}
+
+impl Node {
+    /// Compare this tree with a later snapshot
+    pub fn diff(&self, new: &Node) -> TreeDiff {
+        let old_tree = Tree::new(self.clone());
+        let new_tree = Tree::new(new.clone());
+        let mut changes = Vec::new();
+        let mut labels = HashMap::new();
+
+        for node in new_tree.iter() {
+            labels.insert(node.id, label(node));
+            let id = node.id;
+            let parent = new_tree.parent(id).map(|p| p.id);
+            let Some(old) = old_tree.get(id) else {
+                changes.push(Change::Added { id, parent });
+                continue;
+            };
+            let old_parent = old_tree.parent(id).map(|p| p.id);
+            if old_parent != parent {
+                changes.push(Change::Moved { id, from: old_parent, to: parent });
+            }
+            let workspace = |tree: &Tree| tree.workspace_of(id).filter(|ws| ws.id != id).and_then(|ws| ws.name.clone());
+            let (from, to) = (workspace(&old_tree), workspace(&new_tree));
+            if from != to {
+                changes.push(Change::MovedWorkspace { id, from, to });
+            }
+            if old.layout != node.layout {
+                changes.push(Change::Layout { id, from: old.layout.clone(), to: node.layout.clone() });
+            }
+            if old.focused != node.focused {
+                changes.push(Change::Focused { id, focused: node.focused });
+            }
+            if old.fullscreen_mode != node.fullscreen_mode {
+                changes.push(Change::Fullscreen {
+                    id,
+                    from: old.fullscreen_mode.clone(),
+                    to: node.fullscreen_mode.clone(),
+                });
+            }
+            if old.is_floating() != node.is_floating() {
+                changes.push(Change::Floating { id, floating: node.is_floating() });
+            }
+            if old.rect != node.rect {
+                changes.push(Change::Geometry { id, from: old.rect.clone(), to: node.rect.clone() });
+            }
+            if old.name != node.name {
+                changes.push(Change::Title { id, from: old.name.clone(), to: node.name.clone() });
+            }
+        }
+        for node in old_tree.iter().filter(|node| !new_tree.contains(node.id)) {
+            labels.insert(node.id, label(node));
+            changes.push(Change::Removed { id: node.id });
+        }
+        TreeDiff { changes, labels }
+    }
+}
+
+fn label(node: &Node) -> String {
+    let kind = lower(&node.node_type);
+    match node.app_id.as_ref().or(node.name.as_ref()) {
+        Some(name) => format!("{} #{} \"{}\"", kind, node.id, name),
+        None => format!("{} #{}", kind, node.id),
+    }
+}
+
+fn rect(r: &Rectangle) -> String {
+    format!("{}x{}+{}+{}", r.width, r.height, r.x, r.y)
+}
+
+fn lower<T: fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+fn or_none<T: fmt::Debug>(value: &Option<T>) -> String {
+    value.as_ref().map_or("none".to_string(), lower)
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = |id: Option<u64>| id.map_or("nothing".to_string(), |id| format!("#{}", id));
+        let name = |s: &Option<String>| s.as_ref().map_or("none".to_string(), |s| format!("\"{}\"", s));
+        match self {
+            Change::Added { parent, .. } => write!(f, "added under {}", id(*parent)),
+            Change::Removed { .. } => f.write_str("removed"),
+            Change::Moved { from, to, .. } => write!(f, "moved from {} to {}", id(*from), id(*to)),
+            Change::MovedWorkspace { from, to, .. } => {
+                write!(f, "moved from workspace {} to {}", name(from), name(to))
+            }
+            Change::Layout { from, to, .. } => write!(f, "layout {} -> {}", lower(from), lower(to)),
+            Change::Focused { focused: true, .. } => f.write_str("focused"),
+            Change::Focused { focused: false, .. } => f.write_str("unfocused"),
+            Change::Fullscreen { from, to, .. } => write!(f, "fullscreen {} -> {}", or_none(from), or_none(to)),
+            Change::Floating { floating: true, .. } => f.write_str("floating"),
+            Change::Floating { floating: false, .. } => f.write_str("tiling"),
+            Change::Geometry { from, to, .. } => write!(f, "geometry {} -> {}", rect(from), rect(to)),
+            Change::Title { from, to, .. } => write!(f, "title {} -> {}", name(from), name(to)),
+        }
+    }
+}
+
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            let sign = match change {
+                Change::Added { .. } => '+',
+                Change::Removed { .. } => '-',
+                _ => '~',
+            };
+            writeln!(f, "{} {}: {}", sign, self.label(change.id()), change)?;
+        }
+        Ok(())
+    }
+}
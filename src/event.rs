@@ -0,0 +1,268 @@
+use std::io::{self, Read};
+use std::time::Duration;
+
+use crate::{from_slice, Bar, CommandResult, Input, MessageType, Node, Socket};
+
+/// Bit set in the message type of event replies
+const EVENT_BIT: u32 = 1 << 31;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Workspace,
+    Output,
+    Mode,
+    Window,
+    BarconfigUpdate,
+    Binding,
+    Shutdown,
+    Tick,
+    BarStateUpdate,
+    Input,
+}
+
+impl EventType {
+    fn from_code(code: u32) -> Option<EventType> {
+        Some(match code & !EVENT_BIT {
+            0 => EventType::Workspace,
+            1 => EventType::Output,
+            2 => EventType::Mode,
+            3 => EventType::Window,
+            4 => EventType::BarconfigUpdate,
+            5 => EventType::Binding,
+            6 => EventType::Shutdown,
+            7 => EventType::Tick,
+            0x14 => EventType::BarStateUpdate,
+            0x15 => EventType::Input,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Workspace(WorkspaceEvent),
+    Output(OutputEvent),
+    Mode(ModeEvent),
+    Window(WindowEvent),
+    BarconfigUpdate(Bar),
+    Binding(BindingEvent),
+    Shutdown(ShutdownEvent),
+    Tick(TickEvent),
+    BarStateUpdate(BarStateUpdateEvent),
+    Input(InputEvent),
+}
+
+impl Event {
+    /// Decode an event body given the message type it arrived with
+    pub fn from_message(code: u32, body: Vec<u8>) -> crate::Result<Event> {
+        let Some(event_type) = EventType::from_code(code) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown event type {:#x}", code),
+            ).into());
+        };
+        Ok(match event_type {
+            EventType::Workspace => Event::Workspace(from_slice(body)?),
+            EventType::Output => Event::Output(from_slice(body)?),
+            EventType::Mode => Event::Mode(from_slice(body)?),
+            EventType::Window => Event::Window(from_slice(body)?),
+            EventType::BarconfigUpdate => Event::BarconfigUpdate(from_slice(body)?),
+            EventType::Binding => Event::Binding(from_slice(body)?),
+            EventType::Shutdown => Event::Shutdown(from_slice(body)?),
+            EventType::Tick => Event::Tick(from_slice(body)?),
+            EventType::BarStateUpdate => Event::BarStateUpdate(from_slice(body)?),
+            EventType::Input => Event::Input(from_slice(body)?),
+        })
+    }
+
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::Workspace(_) => EventType::Workspace,
+            Event::Output(_) => EventType::Output,
+            Event::Mode(_) => EventType::Mode,
+            Event::Window(_) => EventType::Window,
+            Event::BarconfigUpdate(_) => EventType::BarconfigUpdate,
+            Event::Binding(_) => EventType::Binding,
+            Event::Shutdown(_) => EventType::Shutdown,
+            Event::Tick(_) => EventType::Tick,
+            Event::BarStateUpdate(_) => EventType::BarStateUpdate,
+            Event::Input(_) => EventType::Input,
+        }
+    }
+}
+
+// Workspace
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceChange { Init, Empty, Focus, Move, Rename, Urgent, Reload, Restored }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct WorkspaceEvent {
+    pub change: WorkspaceChange,
+    pub current: Option<Box<Node>>,
+    pub old: Option<Box<Node>>,
+}
+
+// Output
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct OutputEvent {
+    pub change: String,
+}
+
+// Mode
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ModeEvent {
+    pub change: String,
+    #[serde(default)]
+    pub pango_markup: bool,
+}
+
+// Window
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowChange { New, Close, Focus, Title, FullscreenMode, Move, Floating, Urgent, Mark }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct WindowEvent {
+    pub change: WindowChange,
+    pub container: Box<Node>,
+}
+
+// Binding
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BindingEvent {
+    pub change: String,
+    pub binding: BindingInfo,
+}
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BindingInfo {
+    pub command: String,
+    #[serde(default)]
+    pub event_state_mask: Vec<String>,
+    pub input_code: i64,
+    pub symbol: Option<String>,
+    pub input_type: String,
+}
+
+// Shutdown
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ShutdownEvent {
+    pub change: String,
+}
+
+// Tick
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TickEvent {
+    pub first: bool,
+    pub payload: String,
+}
+
+// Bar state update
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BarStateUpdateEvent {
+    pub id: String,
+    pub visible_by_modifier: bool,
+}
+
+// Input
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputChange { Added, Removed, XkbKeymap, XkbLayout, LibinputConfig }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct InputEvent {
+    pub change: InputChange,
+    pub input: Input,
+}
+
+/// A socket subscribed to events
+///
+/// Bytes are buffered across calls, so a read timeout that fires partway
+/// through a message leaves the stream usable.
+pub struct EventStream {
+    socket: Socket,
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+/// Whether `error` means the connection is gone for good
+pub(crate) fn is_closed(error: &crate::Error) -> bool {
+    matches!(error, crate::Error::IoError(e) if matches!(
+        e.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected
+    ))
+}
+
+/// Remove the first complete message from `buffer`, if there is one
+pub(crate) fn take_message(buffer: &mut Vec<u8>) -> Option<(u32, Vec<u8>)> {
+    let header = buffer.get(..14)?;
+    let size = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let code = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+    if buffer.len() < 14 + size {
+        return None;
+    }
+    let body = buffer[14..14 + size].to_vec();
+    buffer.drain(..14 + size);
+    Some((code, body))
+}
+
+impl EventStream {
+    /// Block until the next event arrives
+    pub fn next_event(&mut self) -> crate::Result<Event> {
+        let mut chunk = [0; 4096];
+        loop {
+            while let Some((code, body)) = take_message(&mut self.buffer) {
+                // Replies to anything but events cannot arrive here, skip them
+                if code & EVENT_BIT != 0 {
+                    return Event::from_message(code, body);
+                }
+            }
+            let read = (self.socket).0.read(&mut chunk)?;
+            if read == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    /// Make `next_event` fail with `WouldBlock` or `TimedOut` after `timeout`
+    ///
+    /// The stream can be read again after such an error.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        (self.socket).0.set_read_timeout(timeout)
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = crate::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.closed {
+            return None;
+        }
+        match self.next_event() {
+            Err(e) if is_closed(&e) => {
+                self.closed = true;
+                None
+            }
+            result => Some(result),
+        }
+    }
+}
+
+impl Socket {
+    /// Subscribe to `events`, turning this connection into an event stream
+    pub fn subscribe(mut self, events: &[EventType]) -> crate::Result<EventStream> {
+        let payload = serde_json::to_string(events).expect("event names serialize");
+        let reply: CommandResult = self.mesg(MessageType::Subscribe, Some(&payload))?;
+        if !reply.success {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("subscription to {} was rejected", payload),
+            ).into());
+        }
+        Ok(EventStream { socket: self, buffer: Vec::new(), closed: false })
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::layout::on;
+use crate::{
+    Amount, Chain, Command, Criteria, Event, Move, Node, Placement, PlacementOptions, Resize, Socket,
+    StateCache, Switch, WindowChange, WorkspaceTarget,
+};
+
+/// Start building a launch of shell command line `command`
+pub fn launch(command: &str) -> Launch {
+    Launch::new(command)
+}
+
+/// A program to start and where to put its window once it appears
+///
+/// The program is started by this process rather than with sway's `exec`,
+/// which does not report the pid. The window is the first new one whose pid
+/// is that process or one of its descendants, or that matches the criteria
+/// given with `matching`, for programs that hand off to an existing instance.
+#[derive(Debug, Clone)]
+pub struct Launch {
+    command: String,
+    workspace: Option<String>,
+    floating: Option<bool>,
+    /// Logical pixels
+    size: Option<(u64, u64)>,
+    placement: Option<Placement>,
+    marks: Vec<String>,
+    criteria: Option<Criteria>,
+    timeout: Duration,
+}
+
+impl Launch {
+    pub fn new(command: &str) -> Launch {
+        Launch {
+            command: command.to_string(),
+            workspace: None,
+            floating: None,
+            size: None,
+            placement: None,
+            marks: Vec::new(),
+            criteria: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn on_workspace(mut self, workspace: &str) -> Launch {
+        self.workspace = Some(workspace.to_string());
+        self
+    }
+
+    /// Float or tile the window; `place` always floats it, whatever is set here
+    pub fn floating(mut self, floating: bool) -> Launch {
+        self.floating = Some(floating);
+        self
+    }
+
+    /// Size in logical pixels
+    pub fn size(mut self, width: u64, height: u64) -> Launch {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Float the window and put it at `placement` on its output, overriding
+    /// `floating(false)`
+    pub fn place(mut self, placement: Placement) -> Launch {
+        self.placement = Some(placement);
+        self
+    }
+
+    /// Add a mark; may be given several times
+    pub fn mark(mut self, mark: &str) -> Launch {
+        self.marks.push(mark.to_string());
+        self
+    }
+
+    /// Also accept a new window matching `criteria`
+    pub fn matching(mut self, criteria: Criteria) -> Launch {
+        self.criteria = Some(criteria);
+        self
+    }
+
+    /// How long to wait for the window, 10 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Launch {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Commands applied to window `id` before any placement
+    pub fn commands(&self, id: u64) -> Option<Chain> {
+        let mut commands = Vec::new();
+        match (self.floating, self.placement) {
+            (_, Some(_)) | (Some(true), None) => commands.push(Command::Floating(Switch::Enable)),
+            (Some(false), None) => commands.push(Command::Floating(Switch::Disable)),
+            (None, None) => {}
+        }
+        if let Some(workspace) = &self.workspace {
+            commands.push(Command::Move(Move::Workspace(WorkspaceTarget::Name(workspace.clone()))));
+        }
+        if let (Some((width, height)), None) = (self.size, self.placement) {
+            commands.push(Command::Resize(Resize::Set {
+                width: Some(Amount::px(width as i64)),
+                height: Some(Amount::px(height as i64)),
+            }));
+        }
+        for mark in &self.marks {
+            commands.push(Command::Mark { mark: mark.clone(), add: true, toggle: false });
+        }
+        let mut commands = commands.into_iter();
+        let chain = on(id, commands.next()?);
+        Some(commands.fold(chain, Chain::then))
+    }
+
+    /// Start the program, wait for its window and set it up
+    pub fn run(self) -> crate::Result<Node> {
+        // Listen before starting so the new window cannot be missed
+        let mut cache = StateCache::new()?;
+        let mut child = process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::null())
+            .process_group(0)
+            .spawn()?;
+        let pid = child.id() as u64;
+        // Reap the shell when it exits so it does not linger as a zombie
+        thread::spawn(move || child.wait());
+
+        let local = self.criteria.as_ref().map(Criteria::local);
+        let mut family = HashSet::from([pid]);
+        let mut fresh = HashSet::new();
+        let window = cache.watch(self.timeout, |cache| {
+            let event = cache.read_event()?;
+            let Event::Window(e) = &event else { return Ok(None) };
+            let window = &e.container;
+            match e.change {
+                WindowChange::New => {
+                    fresh.insert(window.id);
+                    // Only a window from an unknown process is worth a look
+                    // through /proc for processes started since
+                    if window.pid.is_some_and(|p| !family.contains(&p)) {
+                        family = descendants(pid);
+                    }
+                }
+                WindowChange::Close => {
+                    fresh.remove(&window.id);
+                    return Ok(None);
+                }
+                _ if !fresh.contains(&window.id) => return Ok(None),
+                _ => {}
+            }
+            if window.pid.is_some_and(|p| family.contains(&p)) {
+                return Ok(Some(window.as_ref().clone()));
+            }
+            match (&self.criteria, &local) {
+                (Some(criteria), Some(local)) => cache.event_window(&event, criteria, local),
+                _ => Ok(None),
+            }
+        })?;
+
+        let id = window.id;
+        let mut socket = Socket::new()?;
+        if let Some(chain) = self.commands(id) {
+            socket.run(&chain)?;
+        }
+        if let Some(placement) = self.placement {
+            let tree = socket.get_tree()?;
+            let scale = socket.output_scale(&tree, id)?.unwrap_or(1.0);
+            let options = PlacementOptions { size: self.size, scale, ..PlacementOptions::default() };
+            if let Some(geometry) = tree.placement(id, placement, &options) {
+                socket.run(&geometry.commands(id))?;
+            }
+        }
+        Ok(socket.get_tree()?.find(id).cloned().unwrap_or(window))
+    }
+}
+
+/// Pid and parent pid from the contents of `/proc/<pid>/stat`
+pub(crate) fn parse_stat(stat: &str) -> Option<(u64, u64)> {
+    let (pid, rest) = stat.split_once(' ')?;
+    // The command name is in parentheses and may itself contain them
+    let rest = &rest[rest.rfind(')')? + 1..];
+    let ppid = rest.split_whitespace().nth(1)?;
+    Some((pid.parse().ok()?, ppid.parse().ok()?))
+}
+
+/// `root` and every process below it, given (pid, parent pid) pairs
+pub(crate) fn process_tree(processes: &[(u64, u64)], root: u64) -> HashSet<u64> {
+    let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+    for &(pid, ppid) in processes {
+        children.entry(ppid).or_default().push(pid);
+    }
+    let mut found = HashSet::from([root]);
+    let mut stack = vec![root];
+    while let Some(pid) = stack.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if found.insert(child) {
+                stack.push(child);
+            }
+        }
+    }
+    found
+}
+
+/// Running descendants of `root`, read from `/proc`
+fn descendants(root: u64) -> HashSet<u64> {
+    let processes: Vec<(u64, u64)> = fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path().join("stat")).ok())
+        .filter_map(|stat| parse_stat(&stat))
+        .collect();
+    process_tree(&processes, root)
+}
@@ -0,0 +1,428 @@
+use std::collections::HashSet;
+use std::io;
+use std::time::Duration;
+
+use crate::{
+    escape_regex, Amount, Border, Chain, Command, ConId, Criteria, CriteriaError,
+    Criterion, Focus, Layout, Move, Node, NodeBorder, NodeFloating, NodeLayout, NodeType,
+    Pattern, Rectangle, Resize, Socket, Split, StateCache, Switch, WorkspaceTarget,
+};
+
+/// Prefix of the marks used to address containers while restoring
+const MARK_PREFIX: &str = "_ipc_layout_";
+
+/// Regexes a window must match to fill a placeholder, as in i3 layout files
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Swallow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_role: Option<String>,
+}
+
+impl Swallow {
+    /// Exact-match regexes for the window's identity, leaving out the title
+    pub fn from_window(node: &Node) -> Swallow {
+        let exact = |s: &str| format!("^{}$", escape_regex(s));
+        let mut swallow = Swallow::default();
+        match (&node.app_id, &node.window_properties) {
+            (Some(app_id), _) => swallow.app_id = Some(exact(app_id)),
+            (None, Some(props)) => {
+                swallow.class = Some(exact(&props.class));
+                swallow.instance = Some(exact(&props.instance));
+                swallow.window_role = props.window_role.as_deref().map(exact);
+            }
+            (None, None) => {}
+        }
+        swallow
+    }
+
+    pub fn criteria(&self) -> std::result::Result<Criteria, CriteriaError> {
+        let mut criteria = Vec::new();
+        let mut add = |value: &Option<String>, criterion: fn(Pattern) -> Criterion| {
+            if let Some(value) = value {
+                let regex = regex::Regex::new(value)
+                    .map_err(|e| CriteriaError { position: 0, message: e.to_string() })?;
+                criteria.push(criterion(Pattern::Regex(regex)));
+            }
+            Ok(())
+        };
+        add(&self.app_id, Criterion::AppId)?;
+        add(&self.class, Criterion::Class)?;
+        add(&self.instance, Criterion::Instance)?;
+        add(&self.title, Criterion::Title)?;
+        add(&self.window_role, Criterion::WindowRole)?;
+        Ok(Criteria(criteria))
+    }
+}
+
+/// A container in an i3 `append_layout` file
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct LayoutNode {
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<NodeLayout>,
+    /// Share of the parent along its split direction, from 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<NodeBorder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_border_width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floating: Option<NodeFloating>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Position and size of floating containers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rect: Option<Rectangle>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<LayoutNode>,
+    /// Set on placeholders for windows
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swallows: Vec<Swallow>,
+}
+
+impl LayoutNode {
+    /// Whether this is a window placeholder
+    pub fn is_leaf(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Window placeholders below this node, depth first
+    pub fn leaves(&self) -> Vec<&LayoutNode> {
+        if self.is_leaf() {
+            return vec![self];
+        }
+        self.nodes.iter().flat_map(LayoutNode::leaves).collect()
+    }
+}
+
+/// Read a layout file: a sequence of JSON objects, as `i3-save-tree` writes
+pub fn parse_layout(text: &str) -> serde_json::Result<Vec<LayoutNode>> {
+    serde_json::Deserializer::from_str(text).into_iter().collect()
+}
+
+/// Write a layout file that i3's `append_layout` accepts
+pub fn layout_to_string(layout: &[LayoutNode]) -> String {
+    layout
+        .iter()
+        .map(|node| serde_json::to_string_pretty(node).expect("layout serializes"))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+impl Node {
+    /// Describe this workspace's containers as an `append_layout` layout
+    ///
+    /// Several tiled children are wrapped in one container with the
+    /// workspace's layout, since a layout file cannot set it. Floating
+    /// windows follow as `floating_con` entries with their rect.
+    pub fn to_layout(&self) -> Vec<LayoutNode> {
+        let mut layout = Vec::new();
+        let tiled = self.children_layout(self.nodes.iter().collect());
+        if tiled.len() > 1 {
+            layout.push(LayoutNode {
+                layout: Some(self.layout.clone()),
+                percent: Some(1.0),
+                ..blank(NodeType::Con)
+            });
+            layout[0].nodes = tiled;
+        } else {
+            layout.extend(tiled);
+        }
+        for floating in &self.floating_nodes {
+            let mut inner = floating.layout_node(None);
+            inner.node_type = NodeType::Con;
+            inner.percent = Some(1.0);
+            layout.push(LayoutNode {
+                rect: Some(floating.rect.clone()),
+                floating: Some(NodeFloating::UserOn),
+                nodes: vec![inner],
+                ..blank(NodeType::FloatingCon)
+            });
+        }
+        layout
+    }
+
+    fn children_layout(&self, children: Vec<&Node>) -> Vec<LayoutNode> {
+        let size = |node: &Node| match self.layout {
+            NodeLayout::Splith => node.rect.width as f64,
+            NodeLayout::Splitv => node.rect.height as f64,
+            _ => 1.0,
+        };
+        let total: f64 = children.iter().map(|child| size(child)).sum();
+        children
+            .iter()
+            .map(|child| {
+                let percent = child.percent.or((total > 0.0).then(|| size(child) / total));
+                child.layout_node(percent)
+            })
+            .collect()
+    }
+
+    fn layout_node(&self, percent: Option<f64>) -> LayoutNode {
+        let mut node = LayoutNode {
+            percent,
+            border: Some(self.border.clone()),
+            current_border_width: Some(self.current_border_width),
+            marks: self.marks.clone(),
+            ..blank(NodeType::Con)
+        };
+        if self.nodes.is_empty() {
+            node.name = self.name.clone();
+            node.swallows = vec![Swallow::from_window(self)];
+        } else {
+            node.layout = Some(self.layout.clone());
+            node.nodes = self.children_layout(self.nodes.iter().collect());
+        }
+        node
+    }
+}
+
+fn blank(node_type: NodeType) -> LayoutNode {
+    LayoutNode {
+        node_type,
+        layout: None,
+        percent: None,
+        border: None,
+        current_border_width: None,
+        floating: None,
+        name: None,
+        rect: None,
+        marks: Vec::new(),
+        nodes: Vec::new(),
+        swallows: Vec::new(),
+    }
+}
+
+/// Pick a distinct window for every placeholder in `layout`, in depth-first
+/// order, skipping ids in `taken`
+pub fn claim_windows(tree: &Node, layout: &[LayoutNode], taken: &HashSet<u64>) -> std::result::Result<Vec<Option<u64>>, CriteriaError> {
+    let mut taken = taken.clone();
+    let mut claims = Vec::new();
+    for leaf in layout.iter().flat_map(LayoutNode::leaves) {
+        claims.push(claim(tree, leaf, &mut taken)?);
+    }
+    Ok(claims)
+}
+
+fn claim(tree: &Node, leaf: &LayoutNode, taken: &mut HashSet<u64>) -> std::result::Result<Option<u64>, CriteriaError> {
+    for swallow in &leaf.swallows {
+        let criteria = swallow.criteria()?;
+        if let Some(window) = tree.matching(&criteria).into_iter().find(|n| n.is_window() && !taken.contains(&n.id)) {
+            taken.insert(window.id);
+            return Ok(Some(window.id));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn on(id: u64, command: Command) -> Chain {
+    command.with_criteria(Criteria(vec![Criterion::ConId(ConId::Id(id))]))
+}
+
+impl Socket {
+    /// Rebuild `layout` on `workspace` out of existing windows
+    ///
+    /// Placeholders without a matching window are passed to `launch`; if it
+    /// returns a command line, that is run with `exec` and the window is
+    /// waited for up to `timeout`. Returns the window placed for each
+    /// placeholder in depth-first order, `None` where nothing turned up.
+    pub fn restore_layout<F>(
+        &mut self,
+        workspace: &str,
+        layout: &[LayoutNode],
+        mut launch: F,
+        timeout: Duration,
+    ) -> crate::Result<Vec<Option<u64>>>
+    where
+        F: FnMut(&LayoutNode) -> Option<String>,
+    {
+        let invalid = |e: CriteriaError| io::Error::new(io::ErrorKind::InvalidInput, e.to_string());
+        let tree = self.get_tree()?;
+        let mut claims = claim_windows(&tree, layout, &HashSet::new()).map_err(invalid)?;
+
+        // Relaunch what is missing, then wait for all of it at once
+        let leaves: Vec<&LayoutNode> = layout.iter().flat_map(LayoutNode::leaves).collect();
+        let commands: Vec<String> = leaves
+            .iter()
+            .zip(&claims)
+            .filter(|(_, claim)| claim.is_none())
+            .filter_map(|(leaf, _)| launch(leaf))
+            .collect();
+        if !commands.is_empty() {
+            // Listen before launching so that no new window is missed
+            let mut cache = StateCache::new()?;
+            for cmd in commands {
+                self.run(&Chain::from(Command::Exec(cmd)))?;
+            }
+            let waited = cache.wait_for(timeout, |cache| {
+                let mut taken: HashSet<u64> = claims.iter().flatten().copied().collect();
+                for (leaf, claim) in leaves.iter().zip(claims.iter_mut()).filter(|(_, c)| c.is_none()) {
+                    match self::claim(cache.tree(), leaf, &mut taken) {
+                        Ok(id) => *claim = id,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                (!claims.contains(&None)).then_some(Ok(()))
+            });
+            match waited {
+                Ok(claimed) => claimed.map_err(invalid)?,
+                // Arrange whatever did turn up
+                Err(crate::Error::IoError(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Gather the windows on the workspace in layout order, then nest them
+        let target = Move::Workspace(WorkspaceTarget::Name(workspace.to_string()));
+        for (leaf, id) in leaves.iter().zip(&claims) {
+            if let Some(id) = *id {
+                self.run(&on(id, Command::Move(target.clone())))?;
+                self.run(&on(id, Command::Floating(Switch::Disable)))?;
+                for mark in &leaf.marks {
+                    self.run(&on(id, Command::Mark { mark: mark.clone(), add: true, toggle: false }))?;
+                }
+            }
+        }
+        self.run(&Chain::from(Command::Workspace(WorkspaceTarget::Name(workspace.to_string()))))?;
+        let mut tops = Vec::new();
+        let mut start = 0;
+        for node in layout {
+            let count = node.leaves().len();
+            let mut claimed = claims[start..start + count].iter().copied();
+            start += count;
+            if node.node_type != NodeType::FloatingCon {
+                if let Some(top) = arrange(self, node, &mut claimed)? {
+                    tops.push((top, node));
+                }
+                continue;
+            }
+            if let (Some(Some(id)), Some(rect)) = (claimed.next(), &node.rect) {
+                self.run(&on(id, Command::Floating(Switch::Enable))
+                    .then(Command::Resize(Resize::Set {
+                        width: Some(Amount::px(rect.width as i64)),
+                        height: Some(Amount::px(rect.height as i64)),
+                    }))
+                    .then(Command::Move(Move::Position { absolute: true, x: rect.x as i64, y: rect.y as i64 })))?;
+            }
+        }
+        let tree = self.get_tree()?;
+        let ws_layout = tree.workspaces().find(|ws| ws.name.as_deref() == Some(workspace)).map(|ws| ws.layout.clone());
+        resize_children(self, &tops, ws_layout.as_ref())?;
+        Ok(claims)
+    }
+}
+
+/// What arranging needs from the compositor
+pub(crate) trait Arrange {
+    /// Run one chain, failing if it is rejected
+    fn run(&mut self, chain: &Chain) -> crate::Result<()>;
+    /// Id and type of the parent of container `id`
+    fn parent(&mut self, id: u64) -> crate::Result<Option<(u64, NodeType)>>;
+}
+
+impl Arrange for Socket {
+    fn run(&mut self, chain: &Chain) -> crate::Result<()> {
+        Socket::run(self, chain)
+    }
+
+    fn parent(&mut self, id: u64) -> crate::Result<Option<(u64, NodeType)>> {
+        let tree = self.get_tree()?;
+        Ok(crate::Tree::new(tree).parent(id).map(|p| (p.id, p.node_type.clone())))
+    }
+}
+
+/// Build the container for `node` from already gathered windows and
+/// return its id
+pub(crate) fn arrange(
+    socket: &mut impl Arrange,
+    node: &LayoutNode,
+    claimed: &mut impl Iterator<Item = Option<u64>>,
+) -> crate::Result<Option<u64>> {
+    if node.is_leaf() {
+        let id = claimed.next().flatten();
+        if let (Some(id), Some(border)) = (id, &node.border) {
+            let width = node.current_border_width.and_then(|width| u32::try_from(width).ok());
+            let border = match border {
+                NodeBorder::None => Border::None,
+                NodeBorder::Normal => Border::Normal(width),
+                NodeBorder::Pixel => Border::Pixel(width),
+                NodeBorder::Csd => Border::Csd,
+            };
+            socket.run(&on(id, Command::Border(border)))?;
+        }
+        return Ok(id);
+    }
+    let mut tops = Vec::new();
+    for child in &node.nodes {
+        if let Some(top) = arrange(socket, child, claimed)? {
+            tops.push((top, child));
+        }
+    }
+    let Some(&(first, _)) = tops.first() else { return Ok(None) };
+
+    // Wrap the first child in a new container, then move the rest in
+    let layout = node.layout.clone().unwrap_or(NodeLayout::Splith);
+    let split = if layout == NodeLayout::Splith { Split::Horizontal } else { Split::Vertical };
+    let command = match layout {
+        NodeLayout::Splitv => Layout::Splitv,
+        NodeLayout::Stacked => Layout::Stacking,
+        NodeLayout::Tabbed => Layout::Tabbed,
+        _ => Layout::Splith,
+    };
+    socket.run(&on(first, Command::Focus(Focus::Container))
+        .then(Command::Split(split))
+        .then(Command::Layout(command)))?;
+    let Some((parent, parent_type)) = socket.parent(first)? else { return Ok(Some(first)) };
+    let mark = format!("{}{}", MARK_PREFIX, parent);
+    let into = if parent_type == NodeType::Workspace {
+        None
+    } else {
+        socket.run(&on(parent, Command::Mark { mark: mark.clone(), add: true, toggle: false }))?;
+        Some(Move::Mark(mark.clone()))
+    };
+    // Without a mark the workspace itself took the layout and the
+    // children are already in place
+    if let Some(target) = &into {
+        for &(top, _) in &tops[1..] {
+            socket.run(&on(top, Command::Move(target.clone())))?;
+        }
+    }
+    resize_children(socket, &tops, Some(&layout))?;
+    for child in node.marks.iter() {
+        socket.run(&on(parent, Command::Mark { mark: child.clone(), add: true, toggle: false }))?;
+    }
+    if into.is_some() {
+        socket.run(&on(parent, Command::Unmark(Some(mark))))?;
+    }
+    Ok(Some(parent))
+}
+
+/// Apply saved percents along a split
+fn resize_children(socket: &mut impl Arrange, tops: &[(u64, &LayoutNode)], layout: Option<&NodeLayout>) -> crate::Result<()> {
+    if tops.len() < 2 {
+        return Ok(());
+    }
+    for &(id, node) in tops {
+        let Some(percent) = node.percent else { continue };
+        let amount = Some(Amount::ppt((percent * 100.0).round() as i64));
+        let resize = match layout {
+            Some(NodeLayout::Splith) => Resize::Set { width: amount, height: None },
+            Some(NodeLayout::Splitv) => Resize::Set { width: None, height: amount },
+            _ => continue,
+        };
+        socket.run(&on(id, Command::Resize(resize)))?;
+    }
+    Ok(())
+}
@@ -12,13 +12,56 @@ use serde::de::DeserializeOwned;
 use std::env;
 use std::fmt;
 use std::io::{self, Write, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::os::unix::net::UnixStream;
 
 #[cfg(test)]
 mod tests;
+mod bar;
+mod batch;
+mod cache;
+mod cheatsheet;
+mod command;
+mod config;
+mod criteria;
+mod diff;
+mod launch;
+mod layout;
+mod neighbour;
+mod event;
+mod lint;
+mod node;
+mod parser;
+mod placement;
+mod quote;
+mod render;
+mod swaybar;
+mod tree;
 mod types;
+mod version;
+mod wait;
+pub use bar::*;
+pub use batch::*;
+pub use cache::*;
+pub use cheatsheet::*;
+pub use command::*;
+pub use config::*;
+pub use criteria::*;
+pub use diff::*;
+pub use launch::*;
+pub use layout::*;
+pub use neighbour::*;
+pub use event::*;
+pub use lint::*;
+pub use node::*;
+pub use parser::*;
+pub use placement::*;
+pub use quote::*;
+pub use swaybar::*;
+pub use tree::*;
 pub use types::*;
+pub use version::*;
 
 /// Possible Error types
 #[cfg(feature = "pretty_errors")]
@@ -50,6 +93,21 @@ impl fmt::Debug for Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Deserialize a reply body
+#[cfg(feature = "pretty_errors")]
+fn from_slice<T: DeserializeOwned>(message: Vec<u8>) -> Result<T> {
+    serde_json::from_slice::<T>(&message).map_err(|e| {
+        let text = String::from_utf8_lossy(&message).into_owned();
+        Error::DeserializeError(SerdeError::new(text, e))
+    })
+}
+
+#[cfg(not(feature = "pretty_errors"))]
+fn from_slice<T: DeserializeOwned>(message: Vec<u8>) -> Result<T> {
+    serde_json::from_slice::<T>(&message).map_err(Error::DeserializeError)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
     RunCommand = 0,
     GetWorkspaces = 1,
@@ -69,16 +127,43 @@ pub enum MessageType {
     GetSeats = 101,
 }
 
+/// Find the IPC socket of the running sway or i3 instance
+pub fn socket_path() -> io::Result<PathBuf> {
+    for var in ["SWAYSOCK", "I3SOCK"] {
+        if let Some(path) = env::var_os(var) {
+            return Ok(PathBuf::from(path));
+        }
+    }
+    let output = process::Command::new("i3").arg("--get-socketpath").output()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || path.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "SWAYSOCK and I3SOCK are unset and i3 reported no socket path",
+        ));
+    }
+    Ok(PathBuf::from(path))
+}
+
 pub struct Socket(UnixStream);
 
+impl CommandResult {
+    fn outcome(&self) -> std::result::Result<(), CommandError> {
+        if self.success {
+            Ok(())
+        } else if self.parse_error == Some(true) {
+            Err(CommandError::ParseError)
+        } else {
+            Err(CommandError::Failed)
+        }
+    }
+}
+
 impl Socket {
     /// Create a new socket
-    /// Gets path from environment
+    /// Gets path from `SWAYSOCK`, then `I3SOCK`, then `i3 --get-socketpath`
     pub fn new() -> io::Result<Socket> {
-        match env::var("SWAYSOCK") {
-            Ok(val) => Socket::connect(val),
-            Err(_)  => Socket::connect(env!("SWAYSOCK")),
-        }
+        Socket::connect(socket_path()?)
     }
 
     /// Connect to sway server on provided path
@@ -95,34 +180,27 @@ impl Socket {
         if let Some(content) = payload {
             header[6..10].copy_from_slice(&(content.len() as u32).to_ne_bytes());
             self.0.write_all(&header)?;
-            self.0.write_all(&content.as_bytes())?;
+            self.0.write_all(content.as_bytes())?;
         } else {
             self.0.write_all(&header)?;
         }
         Ok(())
     }
 
-    /// Read message
-    #[cfg(feature = "pretty_errors")]
-    fn recv<T: DeserializeOwned>(&mut self) -> Result<T> {
+    /// Read message body
+    fn recv_raw(&mut self) -> io::Result<Vec<u8>> {
         let mut header: [u8; 14] = [0; 14];
         self.0.read_exact(&mut header)?;
         let res_size = u32::from_ne_bytes((&header[6..10]).try_into().unwrap()) as usize;
         let mut message = vec![0; res_size];
         self.0.read_exact(&mut message)?;
-        serde_json::from_slice::<T>(&message).map_err(|e| {
-            Error::DeserializeError(SerdeError::new(String::from_utf8(message).unwrap(), e))
-        })
+        Ok(message)
     }
 
-    #[cfg(not(feature = "pretty_errors"))]
+    /// Read message
     fn recv<T: DeserializeOwned>(&mut self) -> Result<T> {
-        let mut header: [u8; 14] = [0; 14];
-        self.0.read_exact(&mut header)?;
-        let res_size = u32::from_ne_bytes((&header[6..10]).try_into().unwrap()) as usize;
-        let mut message = vec![0; res_size];
-        self.0.read_exact(&mut message)?;
-        Ok(serde_json::from_slice::<T>(&message)?)
+        let message = self.recv_raw()?;
+        from_slice(message)
     }
 
     /// Send a message and receive a reply
@@ -135,20 +213,52 @@ impl Socket {
         self.recv::<T>()
     }
 
-    pub fn run_command(
+    /// Send a message and return the undecoded reply body
+    pub fn raw_request(
+        &mut self,
+        msg_type: MessageType,
+        payload: Option<&str>,
+    ) -> io::Result<Vec<u8>> {
+        self.send(msg_type, payload)?;
+        self.recv_raw()
+    }
+
+    /// Send a message and decode the reply into a caller-defined type
+    pub fn request<T: DeserializeOwned>(
+        &mut self,
+        msg_type: MessageType,
+        payload: Option<&str>,
+    ) -> Result<T> {
+        self.mesg(msg_type, payload)
+    }
+
+    /// Send a message and return the reply as untyped JSON
+    pub fn request_value(
+        &mut self,
+        msg_type: MessageType,
+        payload: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        self.mesg(msg_type, payload)
+    }
+
+    /// Run a command, given as a string, `Command` or `Chain`
+    pub fn run_command<C: fmt::Display + ?Sized>(
         &mut self,
-        cmd: &str
+        cmd: &C
     ) -> Result<Vec<std::result::Result<(), CommandError>>> {
-        let results: Vec<CommandResult> = self.mesg(MessageType::RunCommand, Some(cmd))?;
-        Ok(results.iter().map(|res| {
-            if res.success {
-                Ok(())
-            } else if res.parse_error == Some(true) {
-                Err(CommandError::ParseError)
-            } else {
-                Err(CommandError::Failed)
-            }
-        }).collect())
+        let cmd = cmd.to_string();
+        let results: Vec<CommandResult> = self.mesg(MessageType::RunCommand, Some(&cmd))?;
+        Ok(results.iter().map(CommandResult::outcome).collect())
+    }
+
+    /// Run a command, failing with sway's message if any part is rejected
+    pub(crate) fn run<C: fmt::Display + ?Sized>(&mut self, cmd: &C) -> Result<()> {
+        let cmd = cmd.to_string();
+        let results: Vec<CommandResult> = self.mesg(MessageType::RunCommand, Some(&cmd))?;
+        match results.into_iter().find(|res| res.outcome().is_err()) {
+            Some(res) => Err(io::Error::other(format!("`{}` failed: {}", cmd, res.error.unwrap_or_default())).into()),
+            None => Ok(()),
+        }
     }
 
     pub fn get_workspaces(&mut self) -> Result<Vec<Workspace>> {
@@ -160,6 +270,9 @@ impl Socket {
     pub fn get_tree(&mut self) -> Result<Node> {
         self.mesg(MessageType::GetTree, None)
     }
+    pub fn get_tree_value(&mut self) -> Result<serde_json::Value> {
+        self.request_value(MessageType::GetTree, None)
+    }
     pub fn get_marks(&mut self) -> Result<Vec<String>> {
         self.mesg(MessageType::GetMarks, None)
     }
@@ -172,6 +285,14 @@ impl Socket {
     pub fn get_version(&mut self) -> Result<Version> {
         self.mesg(MessageType::GetVersion, None)
     }
+    /// Which compositor is on the other end of the socket
+    pub fn compositor(&mut self) -> Result<Compositor> {
+        Ok(self.get_version()?.compositor())
+    }
+    /// Whether the connected compositor supports `feature`
+    pub fn supports(&mut self, feature: Feature) -> Result<bool> {
+        Ok(self.get_version()?.supports(feature))
+    }
     pub fn get_binding_modes(&mut self) -> Result<Vec<String>> {
         self.mesg(MessageType::GetBindingModes, None)
     }
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{BindingKind, Command, KeyCombo, Location, Node, ParsedConfig, Script, Socket};
+
+/// Bar settings that hold shell command lines, where `$` belongs to the shell
+const SHELL_SETTINGS: &[&str] = &["status_command", "swaybar_command"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// A line the config reader could not make sense of
+    Syntax(String),
+    /// Same keys and flags bound twice in one mode; holds the earlier location
+    DuplicateBinding { mode: String, keys: KeyCombo, first: Location },
+    /// `mode <name>` naming a mode the config does not define
+    UnknownMode(String),
+    /// `$name` used without a matching `set`
+    UndefinedVariable(String),
+    /// A mode no binding switches to, directly or through other modes
+    UnreachableMode(String),
+    /// `for_window` criteria that match no window in the current tree
+    UnmatchedForWindow(String),
+    /// A command the parser rejects; a warning, as it may be valid sway
+    /// syntax this crate does not model
+    InvalidCommand { command: String, message: String },
+}
+
+impl LintKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintKind::DuplicateBinding { .. }
+            | LintKind::UnreachableMode(_)
+            | LintKind::UnmatchedForWindow(_)
+            | LintKind::InvalidCommand { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A problem found in a config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub location: Option<Location>,
+    pub kind: LintKind,
+}
+
+impl Lint {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::Syntax(message) => f.write_str(message),
+            LintKind::DuplicateBinding { mode, keys, first } => {
+                write!(f, "{} is already bound in mode {} at {}", keys, mode, first)
+            }
+            LintKind::UnknownMode(mode) => write!(f, "mode {} is not defined", mode),
+            LintKind::UndefinedVariable(name) => write!(f, "variable {} is not set", name),
+            LintKind::UnreachableMode(mode) => write!(f, "no binding enters mode {}", mode),
+            LintKind::UnmatchedForWindow(criteria) => {
+                write!(f, "for_window {} matches no current window", criteria)
+            }
+            LintKind::InvalidCommand { command, message } => {
+                write!(f, "command '{}' does not parse: {}", command, message)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity() {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.location {
+            Some(location) => write!(f, "{}: {}: {}", location, severity, self.kind),
+            None => write!(f, "{}: {}", severity, self.kind),
+        }
+    }
+}
+
+impl ParsedConfig {
+    /// Check the config for mistakes; `for_window` rules are checked against
+    /// `tree` when one is given
+    pub fn lint(&self, tree: Option<&Node>) -> Vec<Lint> {
+        let mut lints: Vec<Lint> = self
+            .errors
+            .iter()
+            .map(|e| Lint { location: Some(e.location.clone()), kind: LintKind::Syntax(e.message.clone()) })
+            .collect();
+        let mut push = |location: &Location, kind| lints.push(Lint { location: Some(location.clone()), kind });
+
+        // Bindings: duplicates, commands, mode switches
+        let defined: HashSet<&str> = self.modes.iter().map(|mode| mode.name.as_str()).collect();
+        let mut edges: HashMap<&str, Vec<String>> = HashMap::new();
+        for mode in &self.modes {
+            let mut seen: HashMap<_, &Location> = HashMap::new();
+            for binding in &mode.bindings {
+                let keys = KeyCombo::parse(&binding.keys);
+                let flags = &binding.flags;
+                let identity = (
+                    binding.kind == BindingKind::Code,
+                    keys.clone(),
+                    flags.release,
+                    flags.locked,
+                    flags.inhibited,
+                    flags.input_device.clone(),
+                );
+                match seen.get(&identity) {
+                    Some(first) => push(&binding.location, LintKind::DuplicateBinding {
+                        mode: mode.name.clone(),
+                        keys,
+                        first: (*first).clone(),
+                    }),
+                    None => {
+                        seen.insert(identity, &binding.location);
+                    }
+                }
+                for name in variables(&binding.keys) {
+                    push(&binding.location, LintKind::UndefinedVariable(name));
+                }
+                for target in check_command(&binding.command, &binding.location, &mut push) {
+                    if !defined.contains(target.as_str()) {
+                        push(&binding.location, LintKind::UnknownMode(target.clone()));
+                    }
+                    edges.entry(&mode.name).or_default().push(target);
+                }
+            }
+        }
+
+        // Modes reachable from default
+        let mut reachable: HashSet<&str> = HashSet::from(["default"]);
+        let mut pending = vec!["default"];
+        while let Some(mode) = pending.pop() {
+            for target in edges.get(mode).into_iter().flatten() {
+                if reachable.insert(target) {
+                    pending.push(target);
+                }
+            }
+        }
+
+        for rule in &self.for_windows {
+            for target in check_command(&rule.command, &rule.location, &mut push) {
+                if !defined.contains(target.as_str()) {
+                    push(&rule.location, LintKind::UnknownMode(target));
+                }
+            }
+            if let Some(tree) = tree {
+                if tree.matching(&rule.criteria).is_empty() {
+                    push(&rule.location, LintKind::UnmatchedForWindow(rule.criteria.to_string()));
+                }
+            }
+        }
+        for mode in &self.modes {
+            if !reachable.contains(mode.name.as_str()) {
+                let location = mode.location.as_ref().expect("only default has no location");
+                push(location, LintKind::UnreachableMode(mode.name.clone()));
+            }
+        }
+
+        // Remaining `$` references in other lines
+        let devices = self.outputs.iter().chain(&self.inputs).chain(&self.seats);
+        let settings = devices
+            .flat_map(|device| &device.settings)
+            .chain(self.bars.iter().flat_map(|bar| bar.settings.iter().chain(&bar.colors)))
+            .filter(|directive| !SHELL_SETTINGS.contains(&directive.name.as_str()));
+        for directive in self.directives.iter().chain(settings) {
+            for name in directive.args.iter().flat_map(|arg| variables(arg)) {
+                push(&directive.location, LintKind::UndefinedVariable(name));
+            }
+        }
+        lints
+    }
+}
+
+impl Socket {
+    /// Lint the running config, checking `for_window` rules against the current tree
+    pub fn lint_config(&mut self) -> crate::Result<Vec<Lint>> {
+        let config = self.get_parsed_config()?;
+        let tree = self.get_tree()?;
+        Ok(config.lint(Some(&tree)))
+    }
+}
+
+/// Report parse errors and unexpanded variables in a command and return
+/// the modes it switches to. Arguments of `exec` are left to the shell.
+fn check_command(
+    text: &str,
+    location: &Location,
+    push: &mut impl FnMut(&Location, LintKind),
+) -> Vec<String> {
+    let script = match Script::parse(text) {
+        Ok(script) => script,
+        Err(e) => {
+            push(location, LintKind::InvalidCommand { command: text.to_string(), message: e.to_string() });
+            return Vec::new();
+        }
+    };
+    let mut modes = Vec::new();
+    for command in script.0.iter().flat_map(|chain| &chain.commands) {
+        match command {
+            Command::Exec(_) | Command::ExecAlways(_) => continue,
+            Command::Mode(mode) => modes.push(mode.clone()),
+            _ => {}
+        }
+        for name in variables(&command.to_string()) {
+            push(location, LintKind::UndefinedVariable(name));
+        }
+    }
+    modes
+}
+
+/// `$name` references left in expanded text
+fn variables(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find('$') {
+        rest = &rest[pos + 1..];
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len > 0 {
+            names.push(format!("${}", &rest[..len]));
+        }
+        rest = &rest[len..];
+    }
+    names
+}
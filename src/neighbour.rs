@@ -0,0 +1,156 @@
+use crate::{Direction, Node, NodeType, Rectangle};
+
+/// What to do when nothing lies in the requested direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// Stay put
+    #[default]
+    None,
+    /// Continue from the opposite edge of the current output
+    Output,
+    /// Continue from the opposite edge of the whole output layout
+    Global,
+}
+
+/// Options for [`Node::neighbour`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeighbourOptions {
+    pub wrap: Wrap,
+    /// Consider floating windows as targets
+    pub floating: bool,
+    /// Consider windows on other outputs
+    pub cross_output: bool,
+}
+
+impl Default for NeighbourOptions {
+    fn default() -> NeighbourOptions {
+        NeighbourOptions { wrap: Wrap::None, floating: true, cross_output: true }
+    }
+}
+
+/// Signed copy of a rectangle
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+impl From<&Rectangle> for Rect {
+    fn from(r: &Rectangle) -> Rect {
+        Rect { x: r.x as i64, y: r.y as i64, width: r.width as i64, height: r.height as i64 }
+    }
+}
+
+impl Rect {
+    /// Start and end along the axis of `direction`, and across it
+    fn spans(&self, direction: Direction) -> ((i64, i64), (i64, i64)) {
+        let horizontal = (self.x, self.x + self.width);
+        let vertical = (self.y, self.y + self.height);
+        match direction {
+            Direction::Left | Direction::Right => (horizontal, vertical),
+            Direction::Up | Direction::Down => (vertical, horizontal),
+        }
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+/// Distance from `from` to `to` when moving in `direction`, or `None` if
+/// `to` is not in that direction. Candidates overlapping across the axis
+/// sort first, then by gap along the axis, then by offset across it.
+fn score(from: &Rect, to: &Rect, direction: Direction) -> Option<(bool, i64, i64)> {
+    let ((from_start, from_end), (from_across_start, from_across_end)) = from.spans(direction);
+    let ((to_start, to_end), (to_across_start, to_across_end)) = to.spans(direction);
+    let (from_center, to_center) = (from_start + from_end, to_start + to_end);
+    let gap = match direction {
+        Direction::Right | Direction::Down if to_center > from_center => to_start - from_end,
+        Direction::Left | Direction::Up if to_center < from_center => from_start - to_end,
+        _ => return None,
+    };
+    let across_gap = (to_across_start - from_across_end).max(from_across_start - to_across_end);
+    let offset = ((to_across_start + to_across_end) - (from_across_start + from_across_end)).abs();
+    Some((across_gap > 0, gap.max(0), offset))
+}
+
+/// Place `rect` just outside `bounds` on the side opposite `direction`
+fn wrapped(rect: &Rect, bounds: &Rect, direction: Direction) -> Rect {
+    let mut rect = *rect;
+    match direction {
+        Direction::Right => rect.x = bounds.x - rect.width,
+        Direction::Left => rect.x = bounds.x + bounds.width,
+        Direction::Down => rect.y = bounds.y - rect.height,
+        Direction::Up => rect.y = bounds.y + bounds.height,
+    }
+    rect
+}
+
+impl Node {
+    /// Windows that are currently on screen, with the output holding each
+    fn visible_windows(&self) -> Vec<(&Node, &Node)> {
+        let mut windows = Vec::new();
+        for output in self.outputs() {
+            // The visible workspace comes first in the output's focus order
+            let workspace = output
+                .focus
+                .iter()
+                .find_map(|id| output.nodes.iter().find(|ws| ws.id == *id))
+                .or_else(|| output.nodes.iter().find(|ws| ws.node_type == NodeType::Workspace));
+            let Some(workspace) = workspace else { continue };
+            for window in workspace.windows().filter(|w| w.visible != Some(false)) {
+                windows.push((window, output));
+            }
+        }
+        windows
+    }
+
+    /// Nearest visible window from container `id` in `direction`, by geometry
+    pub fn neighbour(&self, id: u64, direction: Direction, options: &NeighbourOptions) -> Option<&Node> {
+        let from = Rect::from(&self.find(id)?.rect);
+        let windows = self.visible_windows();
+        let output = self
+            .outputs()
+            .find(|output| output.find(id).is_some())
+            .or_else(|| windows.iter().find(|(w, _)| w.id == id).map(|(_, output)| *output));
+        let same_output = |on: &Node| output.is_none_or(|output| output.id == on.id);
+        let candidates: Vec<&(&Node, &Node)> = windows
+            .iter()
+            .filter(|(window, on)| {
+                window.id != id
+                    && (options.floating || !window.is_floating())
+                    && (options.cross_output || same_output(on))
+            })
+            .collect();
+        let best = |from: &Rect, only_this_output: bool| {
+            candidates
+                .iter()
+                .filter(|(_, on)| !only_this_output || same_output(on))
+                .filter_map(|(window, _)| Some((score(from, &Rect::from(&window.rect), direction)?, *window)))
+                .min_by_key(|(score, _)| *score)
+                .map(|(_, window)| window)
+        };
+        if let Some(window) = best(&from, false) {
+            return Some(window);
+        }
+        let bounds = match options.wrap {
+            Wrap::None => return None,
+            Wrap::Output => Rect::from(&output?.rect),
+            Wrap::Global => {
+                let mut rects = self.outputs().map(|output| Rect::from(&output.rect));
+                let first = rects.next()?;
+                rects.fold(first, |acc, rect| acc.union(&rect))
+            }
+        };
+        best(&wrapped(&from, &bounds, direction), options.wrap == Wrap::Output)
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::iter::Chain;
+use std::slice;
+
+use crate::{Node, NodeType};
+
+/// Name of the internal output holding the scratchpad
+pub const INTERNAL_OUTPUT: &str = "__i3";
+
+/// Tiling children followed by floating children
+pub type Children<'a> = Chain<slice::Iter<'a, Node>, slice::Iter<'a, Node>>;
+
+impl Node {
+    /// Direct children, tiling first then floating
+    pub fn children(&self) -> Children<'_> {
+        self.nodes.iter().chain(self.floating_nodes.iter())
+    }
+
+    /// Depth-first, pre-order walk over this node and its descendants
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: vec![self] }
+    }
+
+    /// Breadth-first walk over this node and its descendants
+    pub fn iter_breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst { queue: VecDeque::from([self]) }
+    }
+
+    /// Depth-first walk yielding each node with its depth below `self`
+    pub fn iter_with_depth(&self) -> IterWithDepth<'_> {
+        IterWithDepth { stack: vec![(0, self)] }
+    }
+
+    /// Depth-first walk yielding each node with its ancestors, root first
+    pub fn iter_with_parents(&self) -> IterWithParents<'_> {
+        IterWithParents { stack: vec![(self, 0)], path: Vec::new() }
+    }
+
+    /// Whether this is a leaf container holding a window
+    pub fn is_window(&self) -> bool {
+        matches!(self.node_type, NodeType::Con | NodeType::FloatingCon)
+            && self.nodes.is_empty()
+            && (self.pid.is_some() || self.window.is_some())
+    }
+
+    /// Whether this is the internal output or the scratchpad workspace on it
+    pub fn is_internal(&self) -> bool {
+        self.name.as_deref().is_some_and(|name| name.starts_with(INTERNAL_OUTPUT))
+    }
+
+    /// All windows, including those in the scratchpad
+    pub fn windows(&self) -> impl Iterator<Item = &Node> {
+        self.iter().filter(|node| node.is_window())
+    }
+
+    /// All workspaces, skipping the scratchpad
+    pub fn workspaces(&self) -> impl Iterator<Item = &Node> {
+        self.iter()
+            .filter(|node| node.node_type == NodeType::Workspace && !node.is_internal())
+    }
+
+    /// All outputs, skipping the internal `__i3` output
+    pub fn outputs(&self) -> impl Iterator<Item = &Node> {
+        self.iter()
+            .filter(|node| node.node_type == NodeType::Output && !node.is_internal())
+    }
+
+    /// First node in depth-first order with the given id
+    pub fn find(&self, id: u64) -> Option<&Node> {
+        self.iter().find(|node| node.id == id)
+    }
+
+    /// Chain from this node to the focused node, following the `focus` lists
+    ///
+    /// Stops at a node marked `focused`, which need not be a leaf after
+    /// `focus parent` or on an empty workspace.
+    pub fn focus_path(&self) -> Vec<&Node> {
+        let mut path = vec![self];
+        let mut current = self;
+        while !current.focused {
+            let Some(next) = current.focus.first().and_then(|id| {
+                current.children().find(|child| child.id == *id)
+            }) else {
+                break;
+            };
+            path.push(next);
+            current = next;
+        }
+        path
+    }
+
+    /// Node at the end of the focus chain
+    pub fn focused(&self) -> Option<&Node> {
+        let path = self.focus_path();
+        if path.len() > 1 || self.focused { path.last().copied() } else { None }
+    }
+
+    /// Workspace on the focus chain
+    pub fn focused_workspace(&self) -> Option<&Node> {
+        self.focus_path().into_iter().find(|node| node.node_type == NodeType::Workspace)
+    }
+
+    /// Output on the focus chain
+    pub fn focused_output(&self) -> Option<&Node> {
+        self.focus_path().into_iter().find(|node| node.node_type == NodeType::Output)
+    }
+
+    /// Direct children, most recently focused first
+    ///
+    /// Children missing from `focus` follow in tree order.
+    pub fn children_mru(&self) -> impl Iterator<Item = &Node> {
+        let focused = self.focus.iter()
+            .filter_map(move |id| self.children().find(|child| child.id == *id));
+        let rest = self.children().filter(move |child| !self.focus.contains(&child.id));
+        focused.chain(rest)
+    }
+
+    /// Windows below this node, most recently focused first
+    ///
+    /// Each container's children are visited in `focus` order, so windows
+    /// sharing a recently focused container come before the rest.
+    pub fn windows_mru(&self) -> Vec<&Node> {
+        let mut windows = Vec::new();
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if node.is_window() {
+                windows.push(node);
+            }
+            let children: Vec<&Node> = node.children_mru().collect();
+            stack.extend(children.into_iter().rev());
+        }
+        windows
+    }
+}
+
+/// Depth-first iterator, see [`Node::iter`]
+pub struct Iter<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().rev());
+        Some(node)
+    }
+}
+
+/// Breadth-first iterator, see [`Node::iter_breadth_first`]
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<&'a Node>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node)
+    }
+}
+
+/// Depth-first iterator with depths, see [`Node::iter_with_depth`]
+pub struct IterWithDepth<'a> {
+    stack: Vec<(usize, &'a Node)>,
+}
+
+impl<'a> Iterator for IterWithDepth<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        self.stack.extend(node.children().rev().map(|child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+/// Depth-first iterator with ancestor chains, see [`Node::iter_with_parents`]
+pub struct IterWithParents<'a> {
+    stack: Vec<(&'a Node, usize)>,
+    path: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for IterWithParents<'a> {
+    type Item = (&'a Node, Vec<&'a Node>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, depth) = self.stack.pop()?;
+        self.path.truncate(depth);
+        let parents = self.path.clone();
+        self.path.push(node);
+        self.stack.extend(node.children().rev().map(|child| (child, depth + 1)));
+        Some((node, parents))
+    }
+}
@@ -0,0 +1,794 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{
+    Adjust, Amount, Border, Chain, Command, Criteria, Dimension, Direction, Focus, GapsKind,
+    GapsScope, IdleInhibit, Layout, Move, OutputTarget, Resize, Split, SwapTarget, Switch, Unit,
+    WorkspaceTarget,
+};
+
+/// Sway commands the parser accepts without modelling their arguments
+const OTHER_COMMANDS: &[&str] = &[
+    "allow_tearing", "assign", "bar", "bindcode", "bindgesture", "bindswitch", "bindsym",
+    "client.background", "client.focused", "client.focused_inactive", "client.focused_tab_title",
+    "client.placeholder", "client.unfocused", "client.urgent", "create_output", "default_border",
+    "default_floating_border", "default_orientation", "floating_maximum_size",
+    "floating_minimum_size", "floating_modifier", "focus_follows_mouse",
+    "focus_on_window_activation", "focus_wrapping", "font", "for_window",
+    "force_display_urgency_hint", "force_focus_wrapping", "hide_edge_borders", "include",
+    "max_render_time", "mouse_warping", "new_float", "new_window", "no_focus",
+    "popup_during_fullscreen", "primary_selection", "set", "shortcuts_inhibitor", "show_marks",
+    "smart_borders", "smart_gaps", "swaybg_command", "swaynag_command", "tiling_drag",
+    "tiling_drag_threshold", "title_align", "titlebar_border_thickness", "titlebar_padding",
+    "unbindcode", "unbindgesture", "unbindswitch", "unbindsym", "workspace_auto_back_and_forth",
+    "workspace_layout", "xwayland",
+];
+
+/// `;`-separated chains, as accepted by `run_command`
+#[derive(Debug, Clone, Default)]
+pub struct Script(pub Vec<Chain>);
+
+/// Error from parsing a command string, with the byte offset it occurred at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type ParseResult<T> = std::result::Result<T, ParseError>;
+
+fn error<T>(position: usize, message: impl Into<String>) -> ParseResult<T> {
+    Err(ParseError { position, message: message.into() })
+}
+
+impl Script {
+    /// Parse a full command string, including `;`, `,` and criteria
+    pub fn parse(s: &str) -> ParseResult<Script> {
+        let mut chains = Vec::new();
+        for (start, statement) in split(s, 0, ';')? {
+            if !statement.trim().is_empty() {
+                chains.push(parse_chain(statement, start)?);
+            }
+        }
+        Ok(Script(chains))
+    }
+}
+
+impl Chain {
+    /// Parse optional criteria followed by `,`-separated commands
+    pub fn parse(s: &str) -> ParseResult<Chain> {
+        if let Some(pos) = top_level(s, ';')? {
+            return error(pos, "unexpected ';', use Script::parse for several chains");
+        }
+        parse_chain(s, 0)
+    }
+}
+
+impl Command {
+    /// Parse a single command without criteria or separators
+    pub fn parse(s: &str) -> ParseResult<Command> {
+        for sep in [';', ','] {
+            if let Some(pos) = top_level(s, sep)? {
+                return error(pos, format!("unexpected '{}' in a single command", sep));
+            }
+        }
+        parse_command(s, 0)
+    }
+}
+
+impl FromStr for Script {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        Script::parse(s)
+    }
+}
+
+impl FromStr for Chain {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        Chain::parse(s)
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        Command::parse(s)
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, chain) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", chain)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scan `s` outside quotes and criteria brackets, calling `found` on each `sep`
+fn scan(s: &str, base: usize, sep: char, mut found: impl FnMut(usize)) -> ParseResult<()> {
+    let mut quote: Option<(usize, char)> = None;
+    let mut depth = 0usize;
+    let mut chars = s.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some((_, q)), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some((pos, c)),
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, c) if c == sep && depth == 0 => found(pos),
+            _ => {}
+        }
+    }
+    match quote {
+        Some((pos, _)) => error(base + pos, "unterminated quote"),
+        None => Ok(()),
+    }
+}
+
+/// Offset of the first top-level `sep`
+fn top_level(s: &str, sep: char) -> ParseResult<Option<usize>> {
+    let mut first = None;
+    scan(s, 0, sep, |pos| {
+        first.get_or_insert(pos);
+    })?;
+    Ok(first)
+}
+
+/// Split on top-level `sep`, keeping each piece's absolute offset
+fn split(s: &str, base: usize, sep: char) -> ParseResult<Vec<(usize, &str)>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    scan(s, base, sep, |pos| {
+        pieces.push((base + start, &s[start..pos]));
+        start = pos + sep.len_utf8();
+    })?;
+    pieces.push((base + start, &s[start..]));
+    Ok(pieces)
+}
+
+/// Number of commands sway runs for `s`, which is how many results it sends
+///
+/// Only splits on top-level `;` and `,` without parsing the commands, so it
+/// also covers commands this crate does not model.
+pub(crate) fn count_commands(s: &str) -> usize {
+    let Ok(chains) = split(s, 0, ';') else { return 1 };
+    chains
+        .iter()
+        .flat_map(|&(_, chain)| split(chain, 0, ',').unwrap_or_default())
+        .filter(|(_, command)| !command.trim().is_empty())
+        .count()
+}
+
+fn parse_chain(s: &str, base: usize) -> ParseResult<Chain> {
+    let offset = s.len() - s.trim_start().len();
+    let (criteria, rest, rest_base) = if s[offset..].starts_with('[') {
+        let (criteria, end) = Criteria::parse_prefix(&s[offset..]).map_err(|e| ParseError {
+            position: base + offset + e.position,
+            message: e.message,
+        })?;
+        (Some(criteria), &s[offset + end..], base + offset + end)
+    } else {
+        (None, s, base)
+    };
+    let mut commands = Vec::new();
+    for (start, command) in split(rest, rest_base, ',')? {
+        commands.push(parse_command(command, start)?);
+    }
+    Ok(Chain { criteria, commands })
+}
+
+/// A command argument with quotes removed and escapes resolved
+pub(crate) struct Arg {
+    pub(crate) text: String,
+    pub(crate) position: usize,
+    pub(crate) quoted: bool,
+}
+
+pub(crate) fn tokenize(s: &str, base: usize) -> ParseResult<Vec<Arg>> {
+    let mut args = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    loop {
+        while chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        let Some(&(start, first)) = chars.peek() else {
+            return Ok(args);
+        };
+        let quote = matches!(first, '"' | '\'').then_some(first);
+        if quote.is_some() {
+            chars.next();
+        }
+        let mut text = String::new();
+        loop {
+            match chars.peek().copied() {
+                None if quote.is_some() => return error(base + start, "unterminated quote"),
+                None => break,
+                Some((_, c)) if quote.is_none() && c.is_whitespace() => break,
+                Some((_, c)) if Some(c) == quote => {
+                    chars.next();
+                    break;
+                }
+                Some((_, '\\')) => {
+                    chars.next();
+                    match chars.next() {
+                        Some((_, c @ ('"' | '\'' | '\\'))) => text.push(c),
+                        Some((_, c)) => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        None => text.push('\\'),
+                    }
+                }
+                Some((_, c)) => {
+                    text.push(c);
+                    chars.next();
+                }
+            }
+        }
+        args.push(Arg { text, position: base + start, quoted: quote.is_some() });
+    }
+}
+
+/// Cursor over the arguments of one command
+struct Args<'a> {
+    args: &'a [Arg],
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Args<'a> {
+    fn peek(&self) -> Option<&'a Arg> {
+        self.args.get(self.next)
+    }
+
+    fn position(&self) -> usize {
+        self.peek().map_or(self.end, |arg| arg.position)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.next >= self.args.len()
+    }
+
+    /// Whether the next argument is the keyword `word`
+    ///
+    /// Quotes do not matter: sway strips them before comparing arguments,
+    /// so `workspace "next"` is still the next workspace.
+    fn at(&self, word: &str) -> bool {
+        self.peek().is_some_and(|arg| arg.text.eq_ignore_ascii_case(word))
+    }
+
+    /// Consume the keyword `word` if it is next
+    fn eat(&mut self, word: &str) -> bool {
+        let at = self.at(word);
+        if at {
+            self.next += 1;
+        }
+        at
+    }
+
+    fn expect(&mut self, word: &str) -> ParseResult<()> {
+        if self.eat(word) { Ok(()) } else { error(self.position(), format!("expected '{}'", word)) }
+    }
+
+    fn arg(&mut self, what: &str) -> ParseResult<&'a Arg> {
+        match self.peek() {
+            Some(arg) => {
+                self.next += 1;
+                Ok(arg)
+            }
+            None => error(self.end, format!("expected {}", what)),
+        }
+    }
+
+    /// Next argument as a lowercase keyword
+    fn word(&mut self, what: &str) -> ParseResult<(String, usize)> {
+        let arg = self.arg(what)?;
+        Ok((arg.text.to_ascii_lowercase(), arg.position))
+    }
+
+    fn number<T: FromStr>(&mut self, what: &str) -> ParseResult<T> {
+        let arg = self.arg(what)?;
+        arg.text.parse().or_else(|_| error(arg.position, format!("expected {}", what)))
+    }
+
+    /// Number with an optional unit, either separate or attached as in `10px`
+    fn number_with_unit<T: FromStr>(&mut self, what: &str) -> ParseResult<(T, Option<Unit>)> {
+        let arg = self.arg(what)?;
+        if let Ok(value) = arg.text.parse() {
+            let unit = if self.eat("px") {
+                Some(Unit::Px)
+            } else if self.eat("ppt") {
+                Some(Unit::Ppt)
+            } else {
+                None
+            };
+            return Ok((value, unit));
+        }
+        let (value, unit) = split_unit(&arg.text);
+        match (value.parse(), unit) {
+            (Ok(value), Some(unit)) => Ok((value, Some(unit))),
+            _ => error(arg.position, format!("expected {}", what)),
+        }
+    }
+
+    /// Whether the next argument parses as a number, possibly with a unit
+    fn at_number(&self) -> bool {
+        self.peek().is_some_and(|arg| split_unit(&arg.text).0.parse::<f64>().is_ok())
+    }
+
+    /// Remaining arguments
+    fn rest(&mut self) -> Vec<String> {
+        let rest = self.args[self.next..].iter().map(|arg| arg.text.clone()).collect();
+        self.next = self.args.len();
+        rest
+    }
+
+    /// Remaining arguments joined by spaces, as sway does for names
+    fn joined(&mut self, what: &str) -> ParseResult<String> {
+        if self.is_empty() {
+            return error(self.end, format!("expected {}", what));
+        }
+        Ok(self.rest().join(" "))
+    }
+
+    fn finish(&self) -> ParseResult<()> {
+        match self.peek() {
+            Some(arg) => error(arg.position, format!("unexpected argument '{}'", arg.text)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Split a trailing `px` or `ppt` off a number, as sway's `strtol` does
+fn split_unit(text: &str) -> (&str, Option<Unit>) {
+    let lower = text.to_ascii_lowercase();
+    if lower.ends_with("ppt") {
+        (&text[..text.len() - 3], Some(Unit::Ppt))
+    } else if lower.ends_with("px") {
+        (&text[..text.len() - 2], Some(Unit::Px))
+    } else {
+        (text, None)
+    }
+}
+
+fn direction(word: &str) -> Option<Direction> {
+    match word {
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+fn switch(args: &mut Args<'_>) -> ParseResult<Switch> {
+    let (word, position) = args.word("enable, disable or toggle")?;
+    match word.as_str() {
+        "enable" | "enabled" | "yes" | "on" | "true" | "1" => Ok(Switch::Enable),
+        "disable" | "disabled" | "no" | "off" | "false" | "0" => Ok(Switch::Disable),
+        "toggle" => Ok(Switch::Toggle),
+        _ => error(position, "expected enable, disable or toggle"),
+    }
+}
+
+fn output_target(args: &mut Args<'_>) -> ParseResult<OutputTarget> {
+    let arg = args.arg("an output name or direction")?;
+    let target = match direction(&arg.text.to_ascii_lowercase()) {
+        Some(direction) => OutputTarget::Direction(direction),
+        _ => OutputTarget::Name(arg.text.clone()),
+    };
+    args.finish()?;
+    Ok(target)
+}
+
+/// Workspace target, or `None` for forms only kept as `Command::Other`
+fn workspace_target(args: &mut Args<'_>) -> ParseResult<Option<WorkspaceTarget>> {
+    let keywords = [
+        ("next", WorkspaceTarget::Next),
+        ("prev", WorkspaceTarget::Prev),
+        ("next_on_output", WorkspaceTarget::NextOnOutput),
+        ("prev_on_output", WorkspaceTarget::PrevOnOutput),
+        ("back_and_forth", WorkspaceTarget::BackAndForth),
+    ];
+    if args.args.len() - args.next == 1 {
+        for (word, target) in keywords {
+            if args.eat(word) {
+                return Ok(Some(target));
+            }
+        }
+    }
+    if args.eat("number") {
+        let n = args.number("a workspace number")?;
+        return Ok(if args.is_empty() { Some(WorkspaceTarget::Number(n)) } else { None });
+    }
+    Ok(Some(WorkspaceTarget::Name(args.joined("a workspace name")?)))
+}
+
+/// Length with an optional `px` or `ppt` unit
+fn amount(args: &mut Args<'_>) -> ParseResult<Amount> {
+    let (value, unit) = args.number_with_unit("an amount")?;
+    Ok(Amount { value, unit })
+}
+
+fn parse_command(s: &str, base: usize) -> ParseResult<Command> {
+    let tokens = tokenize(s, base)?;
+    let end = base + s.trim_end().len();
+    let Some(first) = tokens.first() else {
+        return error(end, "expected a command");
+    };
+    let name = first.text.to_ascii_lowercase();
+    let mut args = Args { args: &tokens, next: 1, end };
+    let other = |args: &mut Args<'_>| {
+        args.next = 1;
+        Ok(Command::Other { name: name.clone(), args: args.rest() })
+    };
+    // Text after the command name, verbatim
+    // Sway strips the quotes around a lone argument but does not unescape it
+    let raw = || match tokens.get(1) {
+        Some(arg) => {
+            let rest = s[arg.position - base..].trim_end();
+            match rest.len() {
+                2.. if tokens.len() == 2 && arg.quoted => rest[1..rest.len() - 1].to_string(),
+                _ => rest.to_string(),
+            }
+        }
+        None => String::new(),
+    };
+
+    let command = match name.as_str() {
+        "border" => {
+            let (word, position) = args.word("a border style")?;
+            let width = |args: &mut Args<'_>| {
+                if args.is_empty() { Ok(None) } else { args.number("a border width").map(Some) }
+            };
+            Command::Border(match word.as_str() {
+                "none" => Border::None,
+                "normal" => Border::Normal(width(&mut args)?),
+                "pixel" => Border::Pixel(width(&mut args)?),
+                "csd" => Border::Csd,
+                "toggle" => Border::Toggle,
+                _ => return error(position, "expected none, normal, pixel, csd or toggle"),
+            })
+        }
+        "exec" | "exec_always" => {
+            let cmd = raw();
+            if cmd.is_empty() {
+                return error(end, "expected a command line");
+            }
+            args.rest();
+            if name == "exec" { Command::Exec(cmd) } else { Command::ExecAlways(cmd) }
+        }
+        "exit" => Command::Exit,
+        "floating" => Command::Floating(switch(&mut args)?),
+        "focus" => {
+            if args.is_empty() {
+                Command::Focus(Focus::Container)
+            } else {
+                let (word, position) = args.word("a focus target")?;
+                Command::Focus(match word.as_str() {
+                    "next" if args.eat("sibling") => Focus::NextSibling,
+                    "prev" if args.eat("sibling") => Focus::PrevSibling,
+                    "next" => Focus::Next,
+                    "prev" => Focus::Prev,
+                    "parent" => Focus::Parent,
+                    "child" => Focus::Child,
+                    "floating" => Focus::Floating,
+                    "tiling" => Focus::Tiling,
+                    "mode_toggle" => Focus::ModeToggle,
+                    "output" => Focus::Output(output_target(&mut args)?),
+                    _ => match direction(&word) {
+                        Some(direction) => Focus::Direction(direction),
+                        None => return error(position, format!("unknown focus target '{}'", word)),
+                    },
+                })
+            }
+        }
+        "fullscreen" => {
+            let toggle = if args.is_empty() || args.at("global") {
+                Switch::Toggle
+            } else {
+                switch(&mut args)?
+            };
+            let global = args.eat("global");
+            Command::Fullscreen { toggle, global }
+        }
+        "gaps" => {
+            let (word, position) = args.word("a gaps kind")?;
+            let kind = match word.as_str() {
+                "inner" => GapsKind::Inner,
+                "outer" => GapsKind::Outer,
+                "horizontal" => GapsKind::Horizontal,
+                "vertical" => GapsKind::Vertical,
+                "top" => GapsKind::Top,
+                "right" => GapsKind::Right,
+                "bottom" => GapsKind::Bottom,
+                "left" => GapsKind::Left,
+                _ => return error(position, "expected inner, outer, horizontal, vertical, top, right, bottom or left"),
+            };
+            let (word, position) = args.word("all or current")?;
+            let scope = match word.as_str() {
+                "all" => GapsScope::All,
+                "current" => GapsScope::Current,
+                _ => return error(position, "expected all or current"),
+            };
+            let (word, position) = args.word("set, plus, minus or toggle")?;
+            let adjust = match word.as_str() {
+                "set" => Adjust::Set,
+                "plus" => Adjust::Plus,
+                "minus" => Adjust::Minus,
+                "toggle" => Adjust::Toggle,
+                _ => return error(position, "expected set, plus, minus or toggle"),
+            };
+            let amount = match args.number_with_unit("a gap size")? {
+                (amount, None | Some(Unit::Px)) => amount,
+                (_, Some(Unit::Ppt)) => return other(&mut args),
+            };
+            Command::Gaps { kind, scope, adjust, amount }
+        }
+        "inhibit_idle" => {
+            let (word, position) = args.word("an idle inhibit mode")?;
+            Command::InhibitIdle(match word.as_str() {
+                "focus" => IdleInhibit::Focus,
+                "fullscreen" => IdleInhibit::Fullscreen,
+                "open" => IdleInhibit::Open,
+                "none" => IdleInhibit::None,
+                "visible" => IdleInhibit::Visible,
+                _ => return error(position, "expected focus, fullscreen, open, none or visible"),
+            })
+        }
+        "input" => {
+            let identifier = args.arg("an input identifier")?.text.clone();
+            Command::Input { identifier, args: args.rest() }
+        }
+        "kill" => Command::Kill,
+        "layout" => {
+            let (word, position) = args.word("a layout")?;
+            Command::Layout(match word.as_str() {
+                "default" => Layout::Default,
+                "splith" => Layout::Splith,
+                "splitv" => Layout::Splitv,
+                "stacking" | "stacked" => Layout::Stacking,
+                "tabbed" => Layout::Tabbed,
+                "toggle" if args.args.len() == 3 && args.eat("split") => Layout::ToggleSplit,
+                "toggle" if args.args.len() == 3 && args.eat("all") => Layout::ToggleAll,
+                "toggle" => return other(&mut args),
+                _ => return error(position, format!("unknown layout '{}'", word)),
+            })
+        }
+        "mark" => {
+            let mut add = false;
+            let mut toggle = false;
+            loop {
+                if args.eat("--add") {
+                    add = true;
+                } else if args.eat("--replace") {
+                    add = false;
+                } else if args.eat("--toggle") {
+                    toggle = true;
+                } else {
+                    break;
+                }
+            }
+            Command::Mark { mark: args.joined("a mark")?, add, toggle }
+        }
+        "mode" => Command::Mode(args.joined("a mode name")?),
+        "move" => {
+            if args.at("--no-auto-back-and-forth") {
+                return other(&mut args);
+            }
+            let absolute = args.eat("absolute");
+            if absolute || args.at("position") {
+                args.expect("position")?;
+                return move_position(&mut args, absolute, other);
+            }
+            if let Some(direction) = args.peek().and_then(|arg| direction(&arg.text.to_ascii_lowercase())) {
+                args.next += 1;
+                let px = if args.is_empty() {
+                    None
+                } else {
+                    match args.number_with_unit("a distance")? {
+                        (px, None | Some(Unit::Px)) => Some(px),
+                        (_, Some(Unit::Ppt)) => return other(&mut args),
+                    }
+                };
+                args.finish()?;
+                return Ok(Command::Move(Move::Direction(direction, px)));
+            }
+            if args.eat("workspace") {
+                args.eat("to");
+                args.expect("output")?;
+                return Ok(Command::Move(Move::WorkspaceToOutput(output_target(&mut args)?)));
+            }
+            let _ = args.eat("container") || args.eat("window");
+            args.eat("to");
+            let (word, position) = args.word("a move target")?;
+            Command::Move(match word.as_str() {
+                "workspace" => match workspace_target(&mut args)? {
+                    Some(target) => Move::Workspace(target),
+                    None => return other(&mut args),
+                },
+                "output" => Move::Output(output_target(&mut args)?),
+                "mark" => Move::Mark(args.joined("a mark")?),
+                "scratchpad" => Move::Scratchpad,
+                "position" => return move_position(&mut args, false, other),
+                _ => return error(position, format!("unknown move target '{}'", word)),
+            })
+        }
+        "nop" => {
+            let comment = raw();
+            args.rest();
+            Command::Nop(if comment.is_empty() { None } else { Some(comment) })
+        }
+        "opacity" => {
+            let adjust = if args.eat("plus") {
+                Adjust::Plus
+            } else if args.eat("minus") {
+                Adjust::Minus
+            } else {
+                args.eat("set");
+                Adjust::Set
+            };
+            Command::Opacity { adjust, value: args.number("an opacity")? }
+        }
+        "output" => {
+            let name = args.arg("an output name")?.text.clone();
+            Command::Output { name, args: args.rest() }
+        }
+        "reload" => Command::Reload,
+        "rename" => {
+            args.expect("workspace")?;
+            let Some(to) = args.args[args.next..].iter().position(|arg| arg.text.eq_ignore_ascii_case("to")) else {
+                return error(args.position(), "expected 'to'");
+            };
+            let to = args.next + to;
+            if args.at("number") {
+                return other(&mut args);
+            }
+            let from: Vec<String> = args.args[args.next..to].iter().map(|arg| arg.text.clone()).collect();
+            args.next = to + 1;
+            let from = if from.is_empty() { None } else { Some(from.join(" ")) };
+            Command::Rename { from, to: args.joined("a new workspace name")? }
+        }
+        "resize" => {
+            let (word, position) = args.word("grow, shrink or set")?;
+            match word.as_str() {
+                "grow" | "shrink" => {
+                    let grow = word == "grow";
+                    let (word, position) = args.word("a dimension")?;
+                    let dimension = match word.as_str() {
+                        "width" | "horizontal" => Dimension::Width,
+                        "height" | "vertical" => Dimension::Height,
+                        "up" => Dimension::Up,
+                        "down" => Dimension::Down,
+                        "left" => Dimension::Left,
+                        "right" => Dimension::Right,
+                        _ => return error(position, "expected width, height, up, down, left or right"),
+                    };
+                    if args.is_empty() {
+                        return other(&mut args);
+                    }
+                    let amount = amount(&mut args)?;
+                    if args.at("or") {
+                        return other(&mut args);
+                    }
+                    Command::Resize(if grow {
+                        Resize::Grow(dimension, amount)
+                    } else {
+                        Resize::Shrink(dimension, amount)
+                    })
+                }
+                "set" => {
+                    let mut width = None;
+                    let mut height = None;
+                    if !args.eat("height") {
+                        args.eat("width");
+                        width = Some(amount(&mut args)?);
+                        if args.eat("height") || args.at_number() {
+                            height = Some(amount(&mut args)?);
+                        }
+                    } else {
+                        height = Some(amount(&mut args)?);
+                    }
+                    Command::Resize(Resize::Set { width, height })
+                }
+                _ => return error(position, "expected grow, shrink or set"),
+            }
+        }
+        "scratchpad" => {
+            args.expect("show")?;
+            Command::ScratchpadShow
+        }
+        "seat" => {
+            let name = args.arg("a seat name")?.text.clone();
+            Command::Seat { name, args: args.rest() }
+        }
+        "split" => {
+            let (word, position) = args.word("a split direction")?;
+            Command::Split(match word.as_str() {
+                "h" | "horizontal" => Split::Horizontal,
+                "v" | "vertical" => Split::Vertical,
+                "t" | "toggle" => Split::Toggle,
+                "n" | "none" => Split::None,
+                _ => return error(position, "expected horizontal, vertical, toggle or none"),
+            })
+        }
+        "splith" => Command::Split(Split::Horizontal),
+        "splitv" => Command::Split(Split::Vertical),
+        "splitt" => Command::Split(Split::Toggle),
+        "sticky" => Command::Sticky(switch(&mut args)?),
+        "swap" => {
+            args.expect("container")?;
+            args.expect("with")?;
+            let (word, position) = args.word("id, con_id or mark")?;
+            Command::Swap(match word.as_str() {
+                "id" => SwapTarget::Id(args.number("a window id")?),
+                "con_id" => SwapTarget::ConId(args.number("a container id")?),
+                "mark" => SwapTarget::Mark(args.joined("a mark")?),
+                _ => return error(position, "expected id, con_id or mark"),
+            })
+        }
+        "title_format" => Command::TitleFormat(args.joined("a title format")?),
+        "unmark" => Command::Unmark(if args.is_empty() { None } else { Some(args.joined("a mark")?) }),
+        "urgent" => {
+            if args.at("allow") || args.at("deny") {
+                return other(&mut args);
+            }
+            Command::Urgent(switch(&mut args)?)
+        }
+        "workspace" => {
+            if args.at("--no-auto-back-and-forth") {
+                return other(&mut args);
+            }
+            match workspace_target(&mut args)? {
+                Some(target) => Command::Workspace(target),
+                None => return other(&mut args),
+            }
+        }
+        _ if OTHER_COMMANDS.contains(&name.as_str()) => return other(&mut args),
+        _ => return error(first.position, format!("unknown command '{}'", first.text)),
+    };
+    args.finish()?;
+    Ok(command)
+}
+
+/// Rest of `move [absolute] position ...`
+fn move_position(
+    args: &mut Args<'_>,
+    absolute: bool,
+    other: impl Fn(&mut Args<'_>) -> ParseResult<Command>,
+) -> ParseResult<Command> {
+    let command = if args.eat("center") {
+        Command::Move(Move::Center { absolute })
+    } else if !absolute && (args.eat("cursor") || args.eat("mouse") || args.eat("pointer")) {
+        Command::Move(Move::Cursor)
+    } else {
+        let (x, x_unit) = args.number_with_unit("an x coordinate")?;
+        let (y, y_unit) = args.number_with_unit("a y coordinate")?;
+        if x_unit == Some(Unit::Ppt) || y_unit == Some(Unit::Ppt) {
+            return other(args);
+        }
+        Command::Move(Move::Position { absolute, x, y })
+    };
+    args.finish()?;
+    Ok(command)
+}
@@ -0,0 +1,178 @@
+use crate::layout::on;
+use crate::{Amount, Chain, Command, Move, Node, NodeType, Rectangle, Resize, Socket, Switch};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Where to put a floating window within its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Center,
+    Corner(Corner),
+    /// Column 0, 1 or 2 of three equal columns, full height
+    Third(u32),
+    /// Cell of a grid, counted from the top left
+    Grid { columns: u32, rows: u32, column: u32, row: u32 },
+    /// Keep the size, moving the window just enough to be fully visible
+    Inside,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementOptions {
+    /// Space kept free around and between windows, in logical pixels
+    pub margin: i64,
+    /// Place within the workspace area, which leaves out bars, rather than
+    /// the whole output
+    pub respect_bars: bool,
+    /// Size in logical pixels to use for `Center` and `Corner` instead of
+    /// the current one
+    pub size: Option<(u64, u64)>,
+    /// Output scale, used only to land on whole physical pixels
+    pub scale: f64,
+    /// Logical position of the output, which physical pixels are counted from
+    pub origin: (i64, i64),
+}
+
+impl Default for PlacementOptions {
+    fn default() -> PlacementOptions {
+        PlacementOptions { margin: 0, respect_bars: true, size: None, scale: 1.0, origin: (0, 0) }
+    }
+}
+
+/// Target position and size of a container in logical pixels, title bar included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Geometry {
+    /// Outer box of a container: `rect` leaves out the title bar above it
+    pub fn of(node: &Node) -> Geometry {
+        let title = node.deco_rect.height as i64;
+        Geometry {
+            x: node.rect.x as i64,
+            y: node.rect.y as i64 - title,
+            width: node.rect.width as i64,
+            height: node.rect.height as i64 + title,
+        }
+    }
+
+    /// Commands making container `id` float with this geometry
+    pub fn commands(&self, id: u64) -> Chain {
+        on(id, Command::Floating(Switch::Enable))
+            .then(Command::Resize(Resize::Set {
+                width: Some(Amount::px(self.width)),
+                height: Some(Amount::px(self.height)),
+            }))
+            .then(Command::Move(Move::Position { absolute: true, x: self.x, y: self.y }))
+    }
+}
+
+impl From<&Rectangle> for Geometry {
+    fn from(r: &Rectangle) -> Geometry {
+        Geometry { x: r.x as i64, y: r.y as i64, width: r.width as i64, height: r.height as i64 }
+    }
+}
+
+/// Nearest whole logical pixel that is also a whole physical pixel of an
+/// output starting at `origin`
+fn snap(value: f64, origin: i64, scale: f64) -> i64 {
+    let rounded = value.round() as i64;
+    let whole = |v: i64| ((v - origin) as f64 * scale).fract().abs() < 1e-6;
+    let reach = (scale.ceil() as i64).max(1) * 2;
+    (0..=reach)
+        .flat_map(|d| [rounded - d, rounded + d])
+        .find(|&v| whole(v))
+        .unwrap_or(rounded)
+}
+
+/// Compute where a window currently at `current` goes within `area`
+pub fn place(area: &Geometry, current: &Geometry, placement: Placement, options: &PlacementOptions) -> Geometry {
+    let scale = if options.scale > 0.0 { options.scale } else { 1.0 };
+    let margin = options.margin as f64;
+    let (ax, ay) = (area.x as f64 + margin, area.y as f64 + margin);
+    let (aw, ah) = ((area.width as f64 - 2.0 * margin).max(1.0), (area.height as f64 - 2.0 * margin).max(1.0));
+    let (width, height) = match options.size {
+        Some((w, h)) => (w as f64, h as f64),
+        None => (current.width as f64, current.height as f64),
+    };
+    let (width, height) = (width.min(aw), height.min(ah));
+    let cell = |columns: u32, rows: u32, column: u32, row: u32| {
+        let (columns, rows) = (columns.max(1) as f64, rows.max(1) as f64);
+        let (column, row) = ((column as f64).min(columns - 1.0), (row as f64).min(rows - 1.0));
+        let w = (aw - (columns - 1.0) * margin) / columns;
+        let h = (ah - (rows - 1.0) * margin) / rows;
+        (ax + column * (w + margin), ay + row * (h + margin), w, h)
+    };
+    let (x, y, w, h) = match placement {
+        Placement::Center => (ax + (aw - width) / 2.0, ay + (ah - height) / 2.0, width, height),
+        Placement::Corner(corner) => {
+            let right = matches!(corner, Corner::TopRight | Corner::BottomRight);
+            let bottom = matches!(corner, Corner::BottomLeft | Corner::BottomRight);
+            let x = if right { ax + aw - width } else { ax };
+            let y = if bottom { ay + ah - height } else { ay };
+            (x, y, width, height)
+        }
+        Placement::Third(column) => cell(3, 1, column, 0),
+        Placement::Grid { columns, rows, column, row } => cell(columns, rows, column, row),
+        Placement::Inside => {
+            let (w, h) = ((current.width as f64).min(aw), (current.height as f64).min(ah));
+            let x = (current.x as f64).clamp(ax, ax + aw - w);
+            let y = (current.y as f64).clamp(ay, ay + ah - h);
+            (x, y, w, h)
+        }
+    };
+    let (ox, oy) = options.origin;
+    let (x, y) = (snap(x, ox, scale), snap(y, oy, scale));
+    Geometry { x, y, width: snap(x as f64 + w, ox, scale) - x, height: snap(y as f64 + h, oy, scale) - y }
+}
+
+impl Node {
+    /// Target geometry for container `id`, placed on its own output
+    ///
+    /// The output's position replaces `options.origin`.
+    pub fn placement(&self, id: u64, placement: Placement, options: &PlacementOptions) -> Option<Geometry> {
+        let (node, parents) = self.iter_with_parents().find(|(node, _)| node.id == id)?;
+        let output = parents.iter().find(|n| n.node_type == NodeType::Output)?;
+        let area = if options.respect_bars {
+            parents.iter().find(|n| n.node_type == NodeType::Workspace)?
+        } else {
+            output
+        };
+        let options = PlacementOptions { origin: (output.rect.x as i64, output.rect.y as i64), ..options.clone() };
+        Some(place(&Geometry::from(&area.rect), &Geometry::of(node), placement, &options))
+    }
+}
+
+impl Socket {
+    /// Scale of the output holding container `id`
+    pub(crate) fn output_scale(&mut self, tree: &Node, id: u64) -> crate::Result<Option<f64>> {
+        let output = tree
+            .outputs()
+            .find(|output| output.find(id).is_some())
+            .and_then(|output| output.name.clone());
+        Ok(self
+            .get_outputs()?
+            .into_iter()
+            .find(|o| Some(&o.name) == output.as_ref())
+            .and_then(|o| o.scale))
+    }
+
+    /// Float container `id` and move it into place, using its output's scale
+    pub fn place_window(&mut self, id: u64, placement: Placement, options: &PlacementOptions) -> crate::Result<Option<Geometry>> {
+        let tree = self.get_tree()?;
+        let scale = self.output_scale(&tree, id)?.unwrap_or(options.scale);
+        let options = PlacementOptions { scale, ..options.clone() };
+        let Some(geometry) = tree.placement(id, placement, &options) else { return Ok(None) };
+        self.run(&geometry.commands(id))?;
+        Ok(Some(geometry))
+    }
+}
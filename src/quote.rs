@@ -0,0 +1,40 @@
+use std::borrow::Cow;
+
+/// Render `arg` as a single sway command argument, quoting it only if needed
+///
+/// Arguments are quoted when they are empty or contain whitespace, quotes,
+/// backslashes, `;`, `,` or brackets. Quoting keeps such an argument in one
+/// piece but is removed before sway looks at it, so a name that equals a
+/// keyword in its position, such as `workspace next`, `rename ... to to` or
+/// `mark --add`, cannot be expressed. Sway also still expands `$name` if the
+/// config defines a variable of that name; there is no way to escape it.
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let plain = !arg.is_empty()
+        && !arg.chars().any(|c| {
+            c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';' | ',' | '[' | ']')
+        });
+    if plain {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(quote_always(arg))
+    }
+}
+
+/// Wrap `arg` in double quotes, escaping `"` and `\`
+pub fn quote_always(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escape regex metacharacters so a criteria value matches `s` literally
+pub fn escape_regex(s: &str) -> String {
+    regex::escape(s)
+}
@@ -0,0 +1,133 @@
+use std::fmt::Write;
+
+use crate::{Node, NodeFullscreenMode, NodeLayout, NodeType};
+
+impl Node {
+    /// Layout in sway's compact notation, e.g. `H[V[firefox kitty] emacs]`
+    ///
+    /// Uses the `representation` sway reports and builds the same string
+    /// from the children where it is missing, as on i3.
+    pub fn layout_representation(&self) -> String {
+        if let Some(representation) = &self.representation {
+            return representation.clone();
+        }
+        if self.nodes.is_empty() {
+            return self.app_id.clone()
+                .or_else(|| self.window_properties.as_ref().map(|props| props.class.clone()))
+                .or_else(|| self.name.clone())
+                .unwrap_or_default();
+        }
+        let letter = match self.layout {
+            NodeLayout::Splitv => 'V',
+            NodeLayout::Tabbed => 'T',
+            NodeLayout::Stacked => 'S',
+            _ => 'H',
+        };
+        let children: Vec<String> = self.nodes.iter().map(Node::layout_representation).collect();
+        format!("{}[{}]", letter, children.join(" "))
+    }
+
+    /// Indented outline of the tree, one container per line
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (depth, node) in self.iter_with_depth() {
+            let _ = writeln!(out, "{}{}", "  ".repeat(depth), text_line(node));
+        }
+        out
+    }
+
+    /// Graphviz graph of the tree, coloured by node type
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph tree {\n    node [shape=box, style=filled, fontname=monospace];\n");
+        for node in self.iter() {
+            let mut label = format!("{} #{}", type_name(&node.node_type), node.id);
+            if let Some(name) = &node.name {
+                let _ = write!(label, "\n{}", name);
+            }
+            if !node.nodes.is_empty() {
+                let _ = write!(label, "\n{}", node.layout_representation());
+            }
+            if !node.marks.is_empty() {
+                let _ = write!(label, "\nmarks: {}", node.marks.join(", "));
+            }
+            let focused = if node.focused { ", penwidth=3, fontcolor=red" } else { "" };
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\", fillcolor=\"{}\"{}];",
+                node.id,
+                escape(&label),
+                color(&node.node_type),
+                focused,
+            );
+            for child in &node.nodes {
+                let _ = writeln!(out, "    n{} -> n{};", node.id, child.id);
+            }
+            for child in &node.floating_nodes {
+                let _ = writeln!(out, "    n{} -> n{} [style=dashed];", node.id, child.id);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn type_name(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Root => "root",
+        NodeType::Output => "output",
+        NodeType::Workspace => "workspace",
+        NodeType::Con => "con",
+        NodeType::FloatingCon => "floating_con",
+        NodeType::Dockarea => "dockarea",
+    }
+}
+
+fn color(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Root => "gray80",
+        NodeType::Output => "lightblue",
+        NodeType::Workspace => "palegreen",
+        NodeType::Con => "white",
+        NodeType::FloatingCon => "orange",
+        NodeType::Dockarea => "gray90",
+    }
+}
+
+fn text_line(node: &Node) -> String {
+    let mut line = format!("{} #{}", type_name(&node.node_type), node.id);
+    match node.node_type {
+        NodeType::Root | NodeType::Output | NodeType::Workspace => {
+            if let Some(name) = &node.name {
+                let _ = write!(line, " {}", name);
+            }
+            if node.node_type == NodeType::Workspace && !node.nodes.is_empty() {
+                let _ = write!(line, " {}", node.layout_representation());
+            }
+        }
+        _ if node.nodes.is_empty() => {
+            if let Some(app) = node.app_id.as_ref().or(node.window_properties.as_ref().map(|props| &props.class)) {
+                let _ = write!(line, " {}", app);
+            }
+            if let Some(name) = &node.name {
+                let _ = write!(line, " {:?}", name);
+            }
+        }
+        _ => {
+            let _ = write!(line, " {}", node.layout_representation());
+        }
+    }
+    if !node.marks.is_empty() {
+        let _ = write!(line, " [{}]", node.marks.join(", "));
+    }
+    if matches!(node.fullscreen_mode, Some(NodeFullscreenMode::Full | NodeFullscreenMode::Global)) {
+        line.push_str(" fullscreen");
+    }
+    if node.focused {
+        line.push_str(" *");
+    }
+    line
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
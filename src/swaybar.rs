@@ -0,0 +1,181 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{from_slice, Color};
+
+/// First line a status command writes
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    #[serde(default)]
+    pub click_events: bool,
+    /// Signal to resume output after the bar is shown again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cont_signal: Option<i32>,
+    /// Signal the bar sends when it is hidden
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<i32>,
+}
+
+impl Default for Header {
+    fn default() -> Header {
+        Header { version: 1, click_events: false, cont_signal: None, stop_signal: None }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Markup {
+    None,
+    Pango,
+}
+
+/// Minimum block width, in pixels or as the width of a sample text
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MinWidth {
+    Pixels(u32),
+    Text(String),
+}
+
+/// One block of a status line
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub full_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_top: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_bottom: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_left: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_right: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_width: Option<MinWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<Align>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urgent: Option<bool>,
+    /// Identifies the block in click events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator_block_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub markup: Option<Markup>,
+}
+
+impl Block {
+    pub fn new<S: Into<String>>(full_text: S) -> Block {
+        Block { full_text: full_text.into(), ..Block::default() }
+    }
+}
+
+/// A click on a block, read from the status command's stdin
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ClickEvent {
+    pub name: Option<String>,
+    pub instance: Option<String>,
+    /// Absolute pointer position
+    pub x: i64,
+    pub y: i64,
+    /// X11 button number
+    pub button: u32,
+    /// Linux input event code, not sent by i3bar
+    pub event: Option<u32>,
+    #[serde(default)]
+    pub relative_x: i64,
+    #[serde(default)]
+    pub relative_y: i64,
+    #[serde(default)]
+    pub width: u64,
+    #[serde(default)]
+    pub height: u64,
+    /// Output scale, not sent by i3bar
+    pub scale: Option<f64>,
+    /// Held modifiers, sent by i3bar only
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+/// Writes the header and then status lines as an endless JSON array
+pub struct StatusWriter<W: Write> {
+    writer: W,
+    started: bool,
+}
+
+impl<W: Write> StatusWriter<W> {
+    /// Write `header` and open the array
+    pub fn new(mut writer: W, header: &Header) -> io::Result<StatusWriter<W>> {
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n[\n")?;
+        writer.flush()?;
+        Ok(StatusWriter { writer, started: false })
+    }
+
+    /// Write one status line and flush it
+    pub fn write(&mut self, blocks: &[Block]) -> io::Result<()> {
+        if self.started {
+            self.writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.writer, blocks)?;
+        self.writer.write_all(b"\n")?;
+        self.started = true;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads click events from the endless JSON array on stdin
+pub struct ClickEvents<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> ClickEvents<R> {
+    pub fn new(reader: R) -> ClickEvents<R> {
+        ClickEvents { reader }
+    }
+}
+
+impl<R: BufRead> Iterator for ClickEvents<R> {
+    type Item = crate::Result<ClickEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+            // Events are separated by a leading comma after the opening bracket
+            let event = line.trim().trim_start_matches([',', '[']).trim();
+            if !event.is_empty() && event != "]" {
+                return Some(from_slice(event.as_bytes().to_vec()));
+            }
+        }
+    }
+}
@@ -1,19 +1,141 @@
 use super::*;
-use std::process::Command;
+use std::process;
 
 /// Run swaymsg to corraborate output
 fn swaymsg(command: &str) -> String {
-    let output = Command::new("swaymsg")
+    let output = process::Command::new("swaymsg")
         .arg("-r")
         .arg("-t")
         .arg(command)
         .output()
         .expect("Failed to execute swaymsg")
         .stdout;
-    assert!(output.len() > 0, "swaymsg returned an empty string");
+    assert!(!output.is_empty(), "swaymsg returned an empty string");
     String::from_utf8(output).expect("Invalid utf-8 in swaymsg output")
 }
 
+/// Build the JSON for a tree node with every required field set
+fn node(id: u64, node_type: &str, name: &str, rect: [u64; 4], nodes: Vec<serde_json::Value>) -> serde_json::Value {
+    let [x, y, width, height] = rect;
+    let focus: Vec<u64> = nodes.iter().map(|n| n["id"].as_u64().unwrap()).collect();
+    let layout = match node_type {
+        "root" | "output" => "output",
+        _ if nodes.is_empty() => "none",
+        _ => "splith",
+    };
+    serde_json::json!({
+        "id": id,
+        "name": name,
+        "type": node_type,
+        "border": "none",
+        "current_border_width": 0,
+        "layout": layout,
+        "orientation": "none",
+        "rect": {"x": x, "y": y, "width": width, "height": height},
+        "window_rect": {"x": 0, "y": 0, "width": width, "height": height},
+        "deco_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+        "geometry": {"x": 0, "y": 0, "width": width, "height": height},
+        "urgent": false,
+        "sticky": false,
+        "focused": false,
+        "focus": focus,
+        "nodes": nodes,
+        "floating_nodes": [],
+        "fullscreen_mode": 0
+    })
+}
+
+/// Build the JSON for a wayland window
+fn window(id: u64, app_id: &str, title: &str, rect: [u64; 4]) -> serde_json::Value {
+    let mut window = node(id, "con", title, rect, vec![]);
+    window["app_id"] = serde_json::json!(app_id);
+    window["pid"] = serde_json::json!(1000 + id);
+    window["visible"] = serde_json::json!(true);
+    window["shell"] = serde_json::json!("xdg_shell");
+    window["inhibit_idle"] = serde_json::json!(false);
+    window["idle_inhibitors"] = serde_json::json!({"user": "none", "application": "none"});
+    window["max_render_time"] = serde_json::json!(0);
+    window
+}
+
+/// A two-output tree with a split, a floating window and a scratchpad window
+///
+/// ```text
+/// root 1
+/// ├── __i3 2
+/// │   └── __i3_scratch 3 (floating: scratch 30)
+/// ├── eDP-1 4
+/// │   └── 1 5
+/// │       ├── firefox 10
+/// │       ├── con 11 (splitv)
+/// │       │   ├── kitty 12 (marked term)
+/// │       │   └── emacs 13 (focused)
+/// │       └── (floating: pavucontrol 14)
+/// └── HDMI-A-1 6
+///     └── 2 7
+///         └── mpv 20
+/// ```
+fn fixture_tree() -> Node {
+    let mut split = node(11, "con", "", [960, 0, 960, 1080], vec![
+        window(12, "kitty", "kitty", [960, 0, 960, 540]),
+        window(13, "emacs", "emacs", [960, 540, 960, 540]),
+    ]);
+    split["name"] = serde_json::Value::Null;
+    split["layout"] = serde_json::json!("splitv");
+    split["focus"] = serde_json::json!([13, 12]);
+    split["nodes"][0]["marks"] = serde_json::json!(["term"]);
+    split["nodes"][1]["focused"] = serde_json::json!(true);
+
+    let mut ws1 = node(5, "workspace", "1", [0, 0, 1920, 1080], vec![
+        window(10, "firefox", "Mozilla Firefox", [0, 0, 960, 1080]),
+        split,
+    ]);
+    let mut floating = window(14, "pavucontrol", "Volume Control", [700, 300, 500, 400]);
+    floating["type"] = serde_json::json!("floating_con");
+    ws1["floating_nodes"] = serde_json::json!([floating]);
+    ws1["focus"] = serde_json::json!([11, 10, 14]);
+
+    let mut scratch = node(3, "workspace", "__i3_scratch", [0, 0, 1920, 1080], vec![]);
+    let mut hidden = window(30, "scratch-term", "scratch", [0, 0, 800, 600]);
+    hidden["type"] = serde_json::json!("floating_con");
+    hidden["visible"] = serde_json::json!(false);
+    scratch["floating_nodes"] = serde_json::json!([hidden]);
+    scratch["focus"] = serde_json::json!([30]);
+
+    let mut root = node(1, "root", "root", [0, 0, 4480, 1440], vec![
+        node(2, "output", "__i3", [0, 0, 1920, 1080], vec![scratch]),
+        node(4, "output", "eDP-1", [0, 0, 1920, 1080], vec![ws1]),
+        node(6, "output", "HDMI-A-1", [1920, 0, 2560, 1440], vec![
+            node(7, "workspace", "2", [1920, 0, 2560, 1440], vec![
+                window(20, "mpv", "video.mkv - mpv", [1920, 0, 2560, 1440]),
+            ]),
+        ]),
+    ]);
+    root["focus"] = serde_json::json!([4, 6, 2]);
+    serde_json::from_value(root).unwrap()
+}
+
+/// A socket whose peer answers each request with the next of `replies`,
+/// then hangs up
+fn scripted(replies: Vec<serde_json::Value>) -> Socket {
+    use std::io::{Read, Write};
+    let (ours, mut theirs) = UnixStream::pair().unwrap();
+    std::thread::spawn(move || -> io::Result<()> {
+        for reply in replies {
+            let mut header = [0; 14];
+            theirs.read_exact(&mut header)?;
+            let mut request = vec![0; u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize];
+            theirs.read_exact(&mut request)?;
+            let body = reply.to_string();
+            header[6..10].copy_from_slice(&(body.len() as u32).to_ne_bytes());
+            theirs.write_all(&header)?;
+            theirs.write_all(body.as_bytes())?;
+        }
+        Ok(())
+    });
+    Socket(ours)
+}
+
 mod can {
     use super::*;
 
@@ -103,6 +225,56 @@ mod can {
         socket.get_seats().unwrap();
     }
 
+    #[test]
+    fn raw_request() {
+        let mut socket = Socket::new().unwrap();
+        let raw = socket.raw_request(MessageType::GetVersion, None).unwrap();
+        serde_json::from_slice::<Version>(&raw).unwrap();
+    }
+
+    #[test]
+    fn run_batch() {
+        let mut socket = Socket::new().unwrap();
+        let commands = ["nop first", "nop second, nop third"];
+        let outcomes = socket.run_batch(&commands, BatchPolicy::ReportAll).unwrap();
+        assert!(outcomes.iter().all(|outcome| outcome.succeeded()));
+        assert_eq!(outcomes[1].results.len(), 2);
+    }
+
+    #[test]
+    fn run_batch_reports_invalid_commands() {
+        let mut socket = Socket::new().unwrap();
+        let commands = ["nop", "frobnicate"];
+        let outcomes = socket.run_batch(&commands, BatchPolicy::ReportAll).unwrap();
+        assert!(outcomes[0].succeeded());
+        assert!(!outcomes[1].succeeded());
+    }
+
+    #[test]
+    fn get_parsed_config() {
+        let mut socket = Socket::new().unwrap();
+        let config = socket.get_parsed_config().unwrap();
+        assert_eq!(config.modes[0].name, "default");
+    }
+
+    #[test]
+    fn subscribe() {
+        let mut events = Socket::new().unwrap().subscribe(&[EventType::Tick]).unwrap();
+        assert!(matches!(events.next_event().unwrap(), Event::Tick(TickEvent { first: true, .. })));
+        Socket::new().unwrap().send_tick(Some("ping")).unwrap();
+        match events.next_event().unwrap() {
+            Event::Tick(tick) => assert_eq!(tick.payload, "ping"),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn get_tree_value() {
+        let mut socket = Socket::new().unwrap();
+        let tree = socket.get_tree_value().unwrap();
+        assert!(tree.get("id").is_some());
+    }
+
 }
 
 mod corroborate {
@@ -207,3 +379,1625 @@ mod corroborate {
         assert!(lib_res == shell_res, "swaymsg output does not match lib output");
     }
 }
+
+mod version {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_release() {
+        let version = v("1.8.1");
+        assert_eq!(version.triple(), (1, 8, 1));
+        assert_eq!(version.stage(), Stage::Release);
+        assert_eq!(version.commit(), None);
+    }
+
+    #[test]
+    fn parses_suffixes() {
+        assert_eq!(v("1.9-rc2").stage(), Stage::Rc(2));
+        let dev = v("1.10-dev-9fd1b2c4 (Jan 01 2024, branch 'master')");
+        assert_eq!(dev.triple(), (1, 10, 0));
+        assert_eq!(dev.stage(), Stage::Dev);
+        assert_eq!(dev.commit(), Some("9fd1b2c4"));
+        let i3 = v("4.20-105-g1ab1d8f9 (2021-10-19, branch \"next\")");
+        assert_eq!(i3.stage(), Stage::Release);
+        assert_eq!(i3.commit(), Some("1ab1d8f9"));
+        assert!("sway".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn orders() {
+        assert!(v("1.9-dev") < v("1.9-rc1"));
+        assert!(v("1.9-rc1") < v("1.9-rc2"));
+        assert!(v("1.9-rc2") < v("1.9"));
+        assert!(v("1.9") < v("1.9.1"));
+        assert!(v("1.9.1") < v("1.10-rc1"));
+    }
+
+    #[test]
+    fn gates_features() {
+        assert!(!v("1.4").supports(Feature::GetBindingState));
+        assert!(!v("1.5-rc1").supports(Feature::GetBindingState));
+        assert!(v("1.5").supports(Feature::GetBindingState));
+        assert!(v("1.4").supports(Feature::MaxRenderTime));
+        assert!(!v("1.4").supports(Feature::Sync));
+        assert!(v("4.22").supports(Feature::Sync));
+        assert!(!v("4.22").supports(Feature::BarStateUpdate));
+        assert!(!v("4.18").supports(Feature::GetBindingState));
+    }
+}
+
+mod i3 {
+    use super::*;
+
+    #[test]
+    fn detects_compositor() {
+        let mut version: Version = "4.22 (2023-01-02)".parse().unwrap();
+        assert_eq!(version.compositor(), Compositor::I3);
+        version = "1.8.1".parse().unwrap();
+        assert_eq!(version.compositor(), Compositor::Sway);
+    }
+
+    #[test]
+    fn window_properties() {
+        let props: NodeWindowProperties = serde_json::from_str(r#"{
+            "class": "URxvt",
+            "instance": "urxvt",
+            "window_role": null,
+            "machine": "laptop",
+            "title": "i3: ~",
+            "transient_for": 10485763
+        }"#).unwrap();
+        assert_eq!(props.class, "URxvt");
+        assert_eq!(props.transient_for, Some(10485763));
+    }
+
+    // Trimmed `i3-msg -t get_tree` reply from i3 4.22
+    const TREE: &str = r#"{
+        "id": 94819413807952, "type": "root", "orientation": "horizontal", "scratchpad_state": "none",
+        "percent": null, "urgent": false, "marks": [], "focused": false, "output": null,
+        "layout": "splith", "workspace_layout": "default", "last_split_layout": "splith",
+        "border": "normal", "current_border_width": -1,
+        "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "deco_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+        "window_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+        "geometry": {"x": 0, "y": 0, "width": 0, "height": 0},
+        "name": "root", "window_icon_padding": -1, "window": null, "window_type": null,
+        "nodes": [{
+            "id": 94819413819248, "type": "output", "orientation": "none", "scratchpad_state": "none",
+            "percent": 1.0, "urgent": false, "marks": [], "focused": false, "output": "eDP-1",
+            "layout": "output", "workspace_layout": "default", "last_split_layout": "splith",
+            "border": "normal", "current_border_width": -1,
+            "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+            "deco_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+            "window_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+            "geometry": {"x": 0, "y": 0, "width": 0, "height": 0},
+            "name": "eDP-1", "window_icon_padding": -1, "window": null, "window_type": null,
+            "nodes": [{
+                "id": 94819413850224, "type": "workspace", "orientation": "horizontal",
+                "scratchpad_state": "none", "percent": null, "urgent": false, "marks": [],
+                "focused": false, "output": "eDP-1", "layout": "splith", "workspace_layout": "default",
+                "last_split_layout": "splith", "border": "normal", "current_border_width": -1,
+                "rect": {"x": 0, "y": 0, "width": 1920, "height": 1060},
+                "deco_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+                "window_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+                "geometry": {"x": 0, "y": 0, "width": 0, "height": 0},
+                "name": "1", "num": 1, "gaps": {"inner": 0, "outer": 0, "top": 0, "right": 0, "bottom": 0, "left": 0},
+                "window_icon_padding": -1, "window": null, "window_type": null,
+                "nodes": [{
+                    "id": 94819413883632, "type": "con", "orientation": "none", "scratchpad_state": "none",
+                    "percent": 1.0, "urgent": false, "marks": ["term"], "focused": true, "output": "eDP-1",
+                    "layout": "splith", "workspace_layout": "default", "last_split_layout": "splith",
+                    "border": "pixel", "current_border_width": 2,
+                    "rect": {"x": 0, "y": 0, "width": 1920, "height": 1060},
+                    "deco_rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+                    "window_rect": {"x": 2, "y": 2, "width": 1916, "height": 1056},
+                    "geometry": {"x": 0, "y": 0, "width": 724, "height": 436},
+                    "name": "i3: ~", "window_icon_padding": -1, "window": 18874375, "window_type": "normal",
+                    "window_properties": {"class": "URxvt", "instance": "urxvt", "machine": "laptop",
+                        "title": "i3: ~", "transient_for": null},
+                    "nodes": [], "floating_nodes": [], "focus": [], "fullscreen_mode": 0,
+                    "sticky": false, "floating": "auto_off", "swallows": []
+                }],
+                "floating_nodes": [], "focus": [94819413883632], "fullscreen_mode": 1,
+                "sticky": false, "floating": "auto_off", "swallows": []
+            }],
+            "floating_nodes": [], "focus": [94819413850224], "fullscreen_mode": 0,
+            "sticky": false, "floating": "auto_off", "swallows": []
+        }],
+        "floating_nodes": [], "focus": [94819413819248], "fullscreen_mode": 0,
+        "sticky": false, "floating": "auto_off", "swallows": []
+    }"#;
+
+    #[test]
+    fn tree() {
+        let tree: Node = serde_json::from_str(TREE).unwrap();
+        assert_eq!(tree.current_border_width, -1);
+        let window = tree.focused().unwrap();
+        assert_eq!(window.current_border_width, 2);
+        assert_eq!(window.window_type.as_deref(), Some("normal"));
+        assert_eq!(window.window_properties.as_ref().unwrap().class, "URxvt");
+        assert!(window.app_id.is_none());
+        let criteria = Criteria::parse("[window_type=normal]").unwrap();
+        assert_eq!(tree.matching(&criteria).len(), 1);
+    }
+
+    #[test]
+    fn output() {
+        let output: Output = serde_json::from_str(r#"{
+            "name": "xroot-0",
+            "active": false,
+            "primary": false,
+            "current_workspace": null,
+            "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}
+        }"#).unwrap();
+        assert!(output.modes.is_empty());
+    }
+
+    #[test]
+    fn bar_config() {
+        let bar: Bar = serde_json::from_str(r##"{
+            "id": "bar-0",
+            "mode": "dock",
+            "hidden_state": "hide",
+            "position": "bottom",
+            "status_command": "i3status",
+            "font": "fixed",
+            "workspace_buttons": true,
+            "binding_mode_indicator": true,
+            "verbose": false,
+            "tray_outputs": ["primary"],
+            "separator_symbol": "|",
+            "colors": {"background": "#000000", "statusline": "#ffffffcc"}
+        }"##).unwrap();
+        assert_eq!(bar.gaps, None);
+        assert_eq!(bar.colors.background, Some(Color { r: 0, g: 0, b: 0, a: 255 }));
+        assert_eq!(bar.colors.statusline, Some(Color { r: 255, g: 255, b: 255, a: 204 }));
+        assert_eq!(bar.colors.separator, None);
+    }
+}
+
+#[cfg(feature = "extra_fields")]
+mod extra_fields {
+    use super::*;
+
+    fn unmodelled(report: &mut Vec<String>, what: &str, extra: &serde_json::Map<String, serde_json::Value>) {
+        if !extra.is_empty() {
+            let keys: Vec<&String> = extra.keys().collect();
+            report.push(format!("{} has unmodelled fields: {:?}", what, keys));
+        }
+    }
+
+    fn unmodelled_tree(report: &mut Vec<String>, node: &Node) {
+        unmodelled(report, &format!("node {}", node.id), &node.extra);
+        for child in node.nodes.iter().chain(node.floating_nodes.iter()) {
+            unmodelled_tree(report, child);
+        }
+    }
+
+    #[test]
+    fn fixture_is_modelled() {
+        let mut report = Vec::new();
+        unmodelled_tree(&mut report, &fixture_tree());
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = serde_json::json!({
+            "num": 1,
+            "name": "1",
+            "visible": true,
+            "focused": true,
+            "urgent": false,
+            "output": "eDP-1",
+            "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+            "id": 4,
+            "representation": "H[kitty]"
+        });
+        let workspace: Workspace = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(workspace.extra.get("id"), Some(&serde_json::json!(4)));
+        assert_eq!(serde_json::to_value(&workspace).unwrap(), json);
+    }
+
+    /// Fails on fields the running sway sends that `types.rs` does not model
+    ///
+    /// Newer sway versions add fields over time, so this only holds against
+    /// the sway version the types follow. Workspaces are sent as full tree
+    /// nodes, so fields that `Node` models are not counted for them.
+    #[test]
+    #[ignore = "needs a running sway of the modelled version"]
+    fn drift() {
+        let mut socket = Socket::new().unwrap();
+        let tree = socket.get_tree().unwrap();
+        let mut report = Vec::new();
+        unmodelled_tree(&mut report, &tree);
+        let node_fields = match serde_json::to_value(&tree).unwrap() {
+            serde_json::Value::Object(fields) => fields,
+            _ => unreachable!(),
+        };
+        for workspace in socket.get_workspaces().unwrap() {
+            let mut extra = workspace.extra.clone();
+            extra.retain(|key, _| !node_fields.contains_key(key));
+            unmodelled(&mut report, &format!("workspace {}", workspace.name), &extra);
+        }
+        for output in socket.get_outputs().unwrap() {
+            unmodelled(&mut report, &format!("output {}", output.name), &output.extra);
+        }
+        for input in socket.get_inputs().unwrap() {
+            unmodelled(&mut report, &format!("input {}", input.identifier), &input.extra);
+        }
+        for id in socket.get_bars().unwrap() {
+            unmodelled(&mut report, &format!("bar {}", id), &socket.get_bar_config(&id).unwrap().extra);
+        }
+        assert!(report.is_empty(), "sway sends fields that are not modelled:\n{}", report.join("\n"));
+    }
+}
+
+mod traverse {
+    use super::*;
+
+    fn ids<'a>(nodes: impl Iterator<Item = &'a Node>) -> Vec<u64> {
+        nodes.map(|node| node.id).collect()
+    }
+
+    #[test]
+    fn depth_first() {
+        let tree = fixture_tree();
+        assert_eq!(ids(tree.iter()), [1, 2, 3, 30, 4, 5, 10, 11, 12, 13, 14, 6, 7, 20]);
+    }
+
+    #[test]
+    fn breadth_first() {
+        let tree = fixture_tree();
+        assert_eq!(ids(tree.iter_breadth_first()), [1, 2, 4, 6, 3, 5, 7, 30, 10, 11, 14, 20, 12, 13]);
+    }
+
+    #[test]
+    fn with_depth() {
+        let tree = fixture_tree();
+        let depths: Vec<(usize, u64)> = tree.iter_with_depth().map(|(d, n)| (d, n.id)).take(7).collect();
+        assert_eq!(depths, [(0, 1), (1, 2), (2, 3), (3, 30), (1, 4), (2, 5), (3, 10)]);
+    }
+
+    #[test]
+    fn with_parents() {
+        let tree = fixture_tree();
+        let (_, parents) = tree.iter_with_parents().find(|(n, _)| n.id == 13).unwrap();
+        assert_eq!(ids(parents.into_iter()), [1, 4, 5, 11]);
+        let (_, parents) = tree.iter_with_parents().find(|(n, _)| n.id == 6).unwrap();
+        assert_eq!(ids(parents.into_iter()), [1]);
+    }
+
+    #[test]
+    fn filters() {
+        let tree = fixture_tree();
+        assert_eq!(ids(tree.windows()), [30, 10, 12, 13, 14, 20]);
+        assert_eq!(ids(tree.workspaces()), [5, 7]);
+        assert_eq!(ids(tree.outputs()), [4, 6]);
+    }
+}
+
+mod focus {
+    use super::*;
+
+    #[test]
+    fn path() {
+        let tree = fixture_tree();
+        let path: Vec<u64> = tree.focus_path().iter().map(|n| n.id).collect();
+        assert_eq!(path, [1, 4, 5, 11, 13]);
+        assert!(tree.focused().unwrap().focused);
+        assert_eq!(tree.focused_workspace().unwrap().name.as_deref(), Some("1"));
+        assert_eq!(tree.focused_output().unwrap().name.as_deref(), Some("eDP-1"));
+    }
+
+    #[test]
+    fn focused_container() {
+        // After `focus parent` from emacs
+        let mut tree = fixture_tree();
+        tree.nodes[1].nodes[0].nodes[1].focused = true;
+        tree.nodes[1].nodes[0].nodes[1].nodes[1].focused = false;
+        let path: Vec<u64> = tree.focus_path().iter().map(|n| n.id).collect();
+        assert_eq!(path, [1, 4, 5, 11]);
+        assert_eq!(tree.focused().unwrap().id, 11);
+        let criteria = Criteria::parse("[con_id=__focused__]").unwrap();
+        let matched: Vec<u64> = tree.matching(&criteria).iter().map(|n| n.id).collect();
+        assert_eq!(matched, [11]);
+    }
+
+    #[test]
+    fn focused_empty_workspace() {
+        let mut tree = fixture_tree();
+        tree.nodes[1].nodes[0].nodes[1].nodes[1].focused = false;
+        tree.focus = vec![6, 4, 2];
+        let workspace = &mut tree.nodes[2].nodes[0];
+        workspace.nodes.clear();
+        workspace.focus.clear();
+        workspace.focused = true;
+        assert_eq!(tree.focused().unwrap().id, 7);
+        assert_eq!(tree.focused_workspace().unwrap().name.as_deref(), Some("2"));
+        assert_eq!(tree.focused_output().unwrap().name.as_deref(), Some("HDMI-A-1"));
+        assert_eq!(tree.find(7).unwrap().focused().unwrap().id, 7);
+    }
+
+    #[test]
+    fn mru() {
+        let tree = fixture_tree();
+        let workspace = tree.find(5).unwrap();
+        let children: Vec<u64> = workspace.children_mru().map(|n| n.id).collect();
+        assert_eq!(children, [11, 10, 14]);
+        let windows: Vec<u64> = tree.windows_mru().iter().map(|n| n.id).collect();
+        assert_eq!(windows, [13, 12, 10, 14, 20, 30]);
+    }
+}
+
+mod tree {
+    use super::*;
+
+    fn ids<'a>(nodes: impl IntoIterator<Item = &'a Node>) -> Vec<u64> {
+        nodes.into_iter().map(|node| node.id).collect()
+    }
+
+    #[test]
+    fn lookup() {
+        let tree = Tree::new(fixture_tree());
+        assert_eq!(tree.len(), 14);
+        assert_eq!(tree.get(12).unwrap().app_id.as_deref(), Some("kitty"));
+        assert!(tree.get(99).is_none());
+        assert_eq!(tree.parent(12).unwrap().id, 11);
+        assert!(tree.parent(1).is_none());
+        assert_eq!(ids(tree.children(5)), [10, 11]);
+        assert_eq!(ids(tree.floating_children(5)), [14]);
+    }
+
+    #[test]
+    fn relations() {
+        let tree = Tree::new(fixture_tree());
+        assert_eq!(ids(tree.siblings(10)), [11]);
+        assert_eq!(ids(tree.siblings(14)), Vec::<u64>::new());
+        assert_eq!(ids(tree.path_to_root(13)), [13, 11, 5, 4, 1]);
+        assert_eq!(tree.workspace_of(13).unwrap().id, 5);
+        assert_eq!(tree.workspace_of(5).unwrap().id, 5);
+        assert_eq!(tree.output_of(20).unwrap().id, 6);
+        assert!(tree.workspace_of(4).is_none());
+    }
+}
+
+mod criteria {
+    use super::*;
+
+    fn matching(tree: &Node, criteria: &str) -> Vec<u64> {
+        let criteria: Criteria = criteria.parse().unwrap();
+        tree.matching(&criteria).iter().map(|n| n.id).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let criteria = Criteria::parse(r#"[app_id="firefox" title='^Meet \'x\'' con_id=12 floating]"#).unwrap();
+        assert_eq!(criteria.0.len(), 4);
+        assert_eq!(criteria.to_string(), r#"[app_id="firefox" title="^Meet 'x'" con_id=12 floating]"#);
+        let round_trip = Criteria::parse(&criteria.to_string()).unwrap();
+        assert_eq!(round_trip.to_string(), criteria.to_string());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Criteria::parse("app_id=x").unwrap_err().position, 0);
+        assert_eq!(Criteria::parse("[app_id=x").unwrap_err().position, 9);
+        assert_eq!(Criteria::parse("[colour=red]").unwrap_err().position, 1);
+        assert_eq!(Criteria::parse("[title=\"(\"]").unwrap_err().position, 7);
+        assert_eq!(Criteria::parse("[floating=yes]").unwrap_err().position, 10);
+        assert_eq!(Criteria::parse("[con_id=abc]").unwrap_err().position, 8);
+        assert!(Criteria::parse("[urgent=sometimes]").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let tree = fixture_tree();
+        assert_eq!(matching(&tree, "[app_id=\"^(kitty|emacs)$\"]"), [12, 13]);
+        assert_eq!(matching(&tree, "[title=\"(?i)firefox\"]"), [10]);
+        assert_eq!(matching(&tree, "[floating]"), [30, 14]);
+        assert_eq!(matching(&tree, "[tiling workspace=1]"), [10, 12, 13]);
+        assert_eq!(matching(&tree, "[con_mark=term]"), [12]);
+        assert_eq!(matching(&tree, "[con_id=11]"), [11]);
+        assert_eq!(matching(&tree, "[pid=1020 shell=xdg_shell]"), [20]);
+        assert_eq!(matching(&tree, "[all]").len(), 6);
+    }
+
+    #[test]
+    fn focused() {
+        let tree = fixture_tree();
+        assert_eq!(matching(&tree, "[con_id=__focused__]"), [13]);
+        assert_eq!(matching(&tree, "[app_id=__focused__]"), [13]);
+        assert_eq!(matching(&tree, "[workspace=__focused__]"), [10, 12, 13, 14]);
+    }
+
+    #[test]
+    fn urgent() {
+        let mut tree = fixture_tree();
+        tree.nodes[1].nodes[0].nodes[0].urgent = true;
+        tree.nodes[2].nodes[0].nodes[0].urgent = true;
+        assert_eq!(matching(&tree, "[urgent=first]"), [10]);
+        assert_eq!(matching(&tree, "[urgent=latest]"), [20]);
+    }
+}
+
+mod command {
+    use super::*;
+
+    #[test]
+    fn renders() {
+        assert_eq!(Command::Focus(Focus::Direction(Direction::Left)).to_string(), "focus left");
+        assert_eq!(Command::Layout(Layout::ToggleSplit).to_string(), "layout toggle split");
+        assert_eq!(
+            Command::Resize(Resize::Set { width: Some(Amount::px(800)), height: Some(Amount::ppt(50)) }).to_string(),
+            "resize set width 800 px height 50 ppt"
+        );
+        assert_eq!(
+            Command::Gaps { kind: GapsKind::Inner, scope: GapsScope::Current, adjust: Adjust::Plus, amount: 5 }.to_string(),
+            "gaps inner current plus 5"
+        );
+        assert_eq!(Command::Swap(SwapTarget::ConId(12)).to_string(), "swap container with con_id 12");
+        assert_eq!(
+            Command::Move(Move::Position { absolute: true, x: 10, y: -20 }).to_string(),
+            "move absolute position 10 px -20 px"
+        );
+    }
+
+    #[test]
+    fn quotes_arguments() {
+        let rename = Command::Rename { from: None, to: "1: web, mail; \"misc\"".to_string() };
+        assert_eq!(rename.to_string(), r#"rename workspace to "1: web, mail; \"misc\"""#);
+        let mark = Command::Mark { mark: "x".to_string(), add: true, toggle: false };
+        assert_eq!(mark.to_string(), "mark --add x");
+        assert_eq!(Command::Workspace(WorkspaceTarget::Name(String::new())).to_string(), r#"workspace """#);
+        assert_eq!(Command::Exec("firefox --new-window".to_string()).to_string(), "exec firefox --new-window");
+        assert_eq!(Command::Exec("a; b".to_string()).to_string(), r#"exec "a; b""#);
+        // Sway does not unescape exec lines, so inner quotes stay as written
+        let notify = Command::Exec(r#"notify-send "a; b""#.to_string());
+        assert_eq!(notify.to_string(), r#"exec 'notify-send "a; b"'"#);
+        assert_eq!(Command::parse(&notify.to_string()).unwrap(), notify);
+        assert_eq!(Command::Exec(r#""/opt/my app""#.to_string()).to_string(), r#"exec '"/opt/my app"'"#);
+    }
+
+    #[test]
+    fn chains() {
+        let criteria: Criteria = r#"[app_id="firefox"]"#.parse().unwrap();
+        let chain = Command::Floating(Switch::Enable)
+            .with_criteria(criteria)
+            .then(Command::Move(Move::Center { absolute: false }));
+        assert_eq!(chain.to_string(), r#"[app_id="firefox"] floating enable, move position center"#);
+        assert_eq!(Chain::from(Command::Kill).to_string(), "kill");
+    }
+}
+
+mod parser {
+    use super::*;
+
+    fn canonical(s: &str) -> String {
+        Script::parse(s).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse("focus left").unwrap(), Command::Focus(Focus::Direction(Direction::Left)));
+        assert_eq!(Command::parse("splitv").unwrap(), Command::Split(Split::Vertical));
+        assert_eq!(
+            Command::parse("move window to workspace 1: web").unwrap(),
+            Command::Move(Move::Workspace(WorkspaceTarget::Name("1: web".to_string())))
+        );
+        assert_eq!(
+            Command::parse("resize set 800 px 600 px").unwrap(),
+            Command::Resize(Resize::Set { width: Some(Amount::px(800)), height: Some(Amount::px(600)) })
+        );
+        assert_eq!(
+            Command::parse(r#"rename workspace "old \"one\"" to new"#).unwrap(),
+            Command::Rename { from: Some("old \"one\"".to_string()), to: "new".to_string() }
+        );
+        assert_eq!(
+            Command::parse("smart_gaps on").unwrap(),
+            Command::Other { name: "smart_gaps".to_string(), args: vec!["on".to_string()] }
+        );
+        assert_eq!(Command::parse("exec 'a; b'").unwrap(), Command::Exec("a; b".to_string()));
+    }
+
+    #[test]
+    fn canonicalizes() {
+        assert_eq!(
+            canonical("[app_id=firefox]   floating yes,move position center;focus   parent"),
+            r#"[app_id="firefox"] floating enable, move position center; focus parent"#
+        );
+        assert_eq!(canonical("exec swaymsg 'workspace 2'"), "exec swaymsg 'workspace 2'");
+        assert_eq!(canonical("workspace number 3; layout toggle split;"), "workspace number 3; layout toggle split");
+        assert_eq!(canonical("mark --add --toggle a b"), r#"mark --add --toggle "a b""#);
+        assert_eq!(canonical("fullscreen global"), "fullscreen toggle global");
+        assert_eq!(canonical(r#"title_format "%title; (%app_id)""#), r#"title_format "%title; (%app_id)""#);
+    }
+
+    #[test]
+    fn round_trips() {
+        for s in [
+            "border pixel 2",
+            "gaps outer all minus 4",
+            "move absolute position 10 px 20 px",
+            "swap container with mark \"a b\"",
+            "[con_mark=\"x\" floating] kill, nop hello there",
+            "input type:keyboard xkb_layout \"us,de\"",
+        ] {
+            let once = canonical(s);
+            assert_eq!(canonical(&once), once, "{} did not round-trip", s);
+        }
+    }
+
+    #[test]
+    fn attached_units() {
+        assert_eq!(
+            Command::parse("resize shrink width 10px").unwrap(),
+            Command::Resize(Resize::Shrink(Dimension::Width, Amount::px(10)))
+        );
+        assert_eq!(
+            Command::parse("resize grow height 10ppt").unwrap(),
+            Command::Resize(Resize::Grow(Dimension::Height, Amount::ppt(10)))
+        );
+        assert_eq!(
+            Command::parse("resize set 800px 600PX").unwrap(),
+            Command::Resize(Resize::Set { width: Some(Amount::px(800)), height: Some(Amount::px(600)) })
+        );
+        assert_eq!(Command::parse("move left 10px").unwrap(), Command::Move(Move::Direction(Direction::Left, Some(10))));
+        assert_eq!(
+            Command::parse("move absolute position 10px 20px").unwrap(),
+            Command::Move(Move::Position { absolute: true, x: 10, y: 20 })
+        );
+        assert_eq!(canonical("gaps inner all set 5px"), "gaps inner all set 5");
+        assert!(matches!(Command::parse("move left 10ppt").unwrap(), Command::Other { .. }));
+        assert_eq!(Script::parse("resize grow width 10pt").unwrap_err().position, 18);
+    }
+
+    #[test]
+    fn reports_positions() {
+        let err = Script::parse("kill; floating maybe").unwrap_err();
+        assert_eq!(err.position, 15);
+        let err = Script::parse("kill; frobnicate").unwrap_err();
+        assert_eq!((err.position, err.message.as_str()), (6, "unknown command 'frobnicate'"));
+        assert_eq!(Script::parse("[app_id=x] kill,").unwrap_err().position, 16);
+        assert_eq!(Script::parse("[app_id=\"x] kill").unwrap_err().position, 8);
+        assert_eq!(Script::parse("focus left 3").unwrap_err().position, 11);
+        assert_eq!(Script::parse("resize grow width ten").unwrap_err().position, 18);
+        assert_eq!(Command::parse("kill, kill").unwrap_err().position, 4);
+    }
+}
+
+mod quoting {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(quote("firefox"), "firefox");
+        assert_eq!(quote("1: web"), r#""1: web""#);
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(quote("a;b"), r#""a;b""#);
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote("next"), "next");
+        assert_eq!(quote("--add"), "--add");
+        assert_eq!(escape_regex("a.b(c)"), r"a\.b\(c\)");
+    }
+
+    #[test]
+    fn quotes_do_not_hide_keywords() {
+        // Sway strips quotes before comparing arguments
+        assert_eq!(Command::parse(r#"workspace "next""#).unwrap(), Command::Workspace(WorkspaceTarget::Next));
+        assert_eq!(
+            Command::parse(r#"mark "--add" x"#).unwrap(),
+            Command::Mark { mark: "x".to_string(), add: true, toggle: false }
+        );
+        assert_eq!(
+            Command::parse(r#"focus output "left""#).unwrap(),
+            Command::Focus(Focus::Output(OutputTarget::Direction(Direction::Left)))
+        );
+        assert_eq!(
+            Command::parse(r#"rename workspace a "to" b"#).unwrap(),
+            Command::Rename { from: Some("a".to_string()), to: "b".to_string() }
+        );
+    }
+
+    /// Names sway would read as a keyword or flag however they are quoted
+    fn reserved(s: &str) -> bool {
+        [
+            "to", "number", "next", "prev", "next_on_output", "prev_on_output", "back_and_forth",
+            "--add", "--replace", "--toggle", "--no-auto-back-and-forth",
+        ]
+        .iter()
+        .any(|keyword| s.eq_ignore_ascii_case(keyword))
+    }
+
+    proptest! {
+        #[test]
+        fn arguments_round_trip(s in any::<String>()) {
+            prop_assume!(!reserved(&s));
+            for command in [
+                Command::Mode(s.clone()),
+                Command::Mark { mark: s.clone(), add: true, toggle: false },
+                Command::Workspace(WorkspaceTarget::Name(s.clone())),
+                Command::Rename { from: Some(s.clone()), to: s.clone() },
+                Command::TitleFormat(s.clone()),
+                Command::Swap(SwapTarget::Mark(s.clone())),
+            ] {
+                let rendered = command.to_string();
+                prop_assert_eq!(Command::parse(&rendered).unwrap(), command, "rendered as {}", rendered);
+            }
+        }
+
+        #[test]
+        fn chained_arguments_round_trip(a in any::<String>(), b in any::<String>()) {
+            prop_assume!(!reserved(&a));
+            let script = format!("mark {}; unmark {}, kill", quote(&a), quote(&b));
+            let parsed = Script::parse(&script).unwrap();
+            prop_assert_eq!(&parsed.0[0].commands[0], &Command::Mark { mark: a, add: false, toggle: false });
+            prop_assert_eq!(&parsed.0[1].commands, &vec![Command::Unmark(Some(b)), Command::Kill]);
+        }
+
+        #[test]
+        fn regex_escapes_match_literally(s in any::<String>()) {
+            let regex = regex::Regex::new(&format!("^{}$", escape_regex(&s))).unwrap();
+            prop_assert!(regex.is_match(&s));
+        }
+
+        #[test]
+        fn literal_criteria_round_trip(s in any::<String>()) {
+            let criteria = Criteria(vec![Criterion::Title(Pattern::literal(&s))]);
+            let parsed = Criteria::parse(&criteria.to_string()).unwrap();
+            match &parsed.0[..] {
+                [Criterion::Title(Pattern::Regex(regex))] => prop_assert!(regex.is_match(&s)),
+                other => prop_assert!(false, "unexpected criteria {:?}", other),
+            }
+        }
+    }
+}
+
+mod batch {
+    use super::*;
+
+    fn result(success: bool) -> CommandResult {
+        CommandResult { success, parse_error: None, error: (!success).then(|| "failed".to_string()) }
+    }
+
+    #[test]
+    fn pairs_results() {
+        let commands = ["a", "b, c", "d"];
+        let outcomes = crate::batch::pair(&commands, &[1, 2, 1], vec![result(true), result(true), result(false)]);
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes[0].succeeded());
+        assert_eq!(*outcomes[1].command, "b, c");
+        assert!(!outcomes[1].succeeded());
+        assert_eq!(outcomes[1].errors().collect::<Vec<_>>(), ["failed"]);
+        assert_eq!(outcomes[2].results, [None]);
+    }
+
+    #[test]
+    fn counts_without_parsing() {
+        use crate::parser::count_commands;
+        assert_eq!(count_commands("resize grow width 10px"), 1);
+        assert_eq!(count_commands("[app_id=\"a,b\"] frobnicate x, kill; nop \"c;d\""), 3);
+        assert_eq!(count_commands("nop;"), 1);
+    }
+}
+
+mod config {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# comment
+set $mod Mod4
+set $term kitty
+set $ws1 "1: web"
+
+bindsym $mod+Return exec $term
+bindsym --release --locked $mod+Shift+q kill
+bindcode --input-device=1:1:kbd 24 \
+    exec notify-send q
+
+mode "resize" {
+    bindsym h resize shrink width 10px
+    bindsym Escape mode default
+}
+mode --pango_markup "<b>system</b>" bindsym r reload
+
+output eDP-1 scale 2
+output eDP-1 {
+    pos 0 0
+    bg ~/wall.png fill
+}
+input type:keyboard xkb_layout us
+
+bar main {
+    status_command i3status
+    colors {
+        background #000000
+    }
+}
+
+assign [app_id="firefox"] → workspace $ws1
+assign [class="^Steam$"] output HDMI-A-1
+for_window [app_id="pavucontrol"] floating enable
+exec mako
+exec_always "pkill kanshi; kanshi"
+gaps inner 5
+"#;
+
+    #[test]
+    fn parses_sections() {
+        let config = ParsedConfig::parse(CONFIG);
+        assert!(config.errors.is_empty(), "{:?}", config.errors);
+        assert_eq!(config.variable("$ws1"), Some("\"1: web\""));
+
+        let default = config.mode("default").unwrap();
+        assert_eq!(default.bindings.len(), 3);
+        assert_eq!(default.bindings[0].keys, "Mod4+Return");
+        assert_eq!(default.bindings[0].command, "exec kitty");
+        assert!(default.bindings[1].flags.release && default.bindings[1].flags.locked);
+        assert_eq!(default.bindings[2].kind, BindingKind::Code);
+        assert_eq!(default.bindings[2].flags.input_device.as_deref(), Some("1:1:kbd"));
+        assert_eq!(default.bindings[2].command, "exec notify-send q");
+        assert_eq!(default.bindings[2].location.line, 9);
+
+        let resize = config.mode("resize").unwrap();
+        assert_eq!(resize.bindings.len(), 2);
+        assert_eq!(resize.bindings[1].command, "mode default");
+        let system = config.mode("<b>system</b>").unwrap();
+        assert_eq!(system.bindings[0].command, "reload");
+
+        assert_eq!(config.outputs.len(), 1);
+        let names: Vec<_> = config.outputs[0].settings.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["scale", "pos", "bg"]);
+        assert_eq!(config.inputs[0].identifier, "type:keyboard");
+
+        assert_eq!(config.bars[0].id.as_deref(), Some("main"));
+        assert_eq!(config.bars[0].settings[0].args, ["i3status"]);
+        assert_eq!(config.bars[0].colors[0].name, "background");
+
+        assert_eq!(config.assigns[0].target, AssignTarget::Workspace(WorkspaceTarget::Name("1: web".to_string())));
+        assert_eq!(config.assigns[1].target, AssignTarget::Output(OutputTarget::Name("HDMI-A-1".to_string())));
+        assert_eq!(config.for_windows[0].command, "floating enable");
+        assert_eq!(config.for_windows[0].criteria.to_string(), r#"[app_id="pavucontrol"]"#);
+        assert_eq!(config.execs[1].command, "pkill kanshi; kanshi");
+        assert!(config.execs[1].always);
+        assert_eq!(config.directives[0].name, "gaps");
+    }
+
+    #[test]
+    fn reports_errors() {
+        let config = ParsedConfig::parse("bindsym a\nmode x {\nbindsym b nop\n");
+        assert_eq!(config.errors.len(), 2, "{:?}", config.errors);
+        let lines: Vec<_> = config.errors.iter().map(|e| e.location.line).collect();
+        assert!(lines.contains(&1) && lines.contains(&3), "{:?}", config.errors);
+        assert_eq!(config.mode("x").unwrap().bindings.len(), 1);
+    }
+
+    #[test]
+    fn resolves_includes() {
+        let dir = std::env::temp_dir().join(format!("ipc-config-{}", process::id()));
+        std::fs::create_dir_all(dir.join("config.d")).unwrap();
+        std::fs::write(dir.join("config"), "set $mod Mod1\ninclude config.d/*.conf\ninclude config\n").unwrap();
+        std::fs::write(dir.join("config.d/10-keys.conf"), "bindsym $mod+x kill\n").unwrap();
+        std::fs::write(dir.join("config.d/README"), "bindsym y kill\n").unwrap();
+        let config = ParsedConfig::load(dir.join("config")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(config.errors.is_empty(), "{:?}", config.errors);
+        assert_eq!(config.includes[0].files, [dir.join("config.d/10-keys.conf")]);
+        assert!(config.includes[1].files.is_empty());
+        let binding = &config.mode("default").unwrap().bindings[0];
+        assert_eq!(binding.keys, "Mod1+x");
+        assert_eq!(binding.location.file.as_deref(), Some(dir.join("config.d/10-keys.conf").as_path()));
+    }
+
+    #[test]
+    fn expands_environment_in_includes() {
+        use crate::config::expand_env;
+        let lookup = |name: &str| (name == "XDG_CONFIG_HOME").then(|| "/home/u/.config".to_string());
+        assert_eq!(expand_env("$XDG_CONFIG_HOME/sway/config.d/*", lookup), "/home/u/.config/sway/config.d/*");
+        assert_eq!(expand_env("${XDG_CONFIG_HOME}x/$UNSET/a$", lookup), "/home/u/.configx//a$");
+
+        // `set` variables are substituted first, so they may hold a reference
+        let dir = std::env::temp_dir().join(format!("ipc-config-env-{}", process::id()));
+        std::fs::create_dir_all(dir.join("config.d")).unwrap();
+        std::fs::write(dir.join("config.d/keys"), "bindsym Mod4+x kill\n").unwrap();
+        let text = format!("set $conf $TMPDIR_{}\ninclude $conf/config.d/*\n", process::id());
+        std::env::set_var(format!("TMPDIR_{}", process::id()), &dir);
+        std::fs::write(dir.join("config"), text).unwrap();
+        let config = ParsedConfig::load(dir.join("config")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(config.errors.is_empty(), "{:?}", config.errors);
+        assert_eq!(config.includes[0].files, [dir.join("config.d/keys")]);
+    }
+}
+
+mod cheatsheet {
+    use super::*;
+
+    fn sheet() -> CheatSheet {
+        let config = ParsedConfig::parse(
+            "set $mod Mod4\n\
+             bindsym $mod+shift+Q kill\n\
+             bindsym --release --locked Super+Ctrl+l exec swaylock\n\
+             bindsym Mod4++ exec 'a | b'\n\
+             mode resize {\n\
+             bindsym Escape mode default\n\
+             }\n",
+        );
+        CheatSheet::new(&config, &["default".to_string(), "resize".to_string(), "empty".to_string()])
+    }
+
+    #[test]
+    fn normalizes_keys() {
+        assert_eq!(KeyCombo::parse("shift+Super+Q").to_string(), "Mod4+Shift+q");
+        assert_eq!(KeyCombo::parse("Alt+control+Return").to_string(), "Ctrl+Mod1+Return");
+        assert_eq!(KeyCombo::parse("Mod4++").to_string(), "Mod4+plus");
+    }
+
+    #[test]
+    fn lists_modes() {
+        let sheet = sheet();
+        let names: Vec<_> = sheet.modes.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["default", "resize", "empty"]);
+        let lock = &sheet.modes[0].bindings[1];
+        assert_eq!(lock.keys.to_string(), "Mod4+Ctrl+l");
+        assert_eq!(lock.flags, ["--release", "--locked"]);
+        assert_eq!(lock.command, "exec swaylock");
+    }
+
+    #[test]
+    fn exports() {
+        let sheet = sheet();
+        let json: serde_json::Value = serde_json::from_str(&sheet.to_json()).unwrap();
+        assert_eq!(json["modes"][1]["bindings"][0]["keys"]["key"], "Escape");
+        assert!(sheet.to_markdown().contains("| `Mod4+plus` |  | `exec 'a \\| b'` |"));
+        assert_eq!(
+            sheet.to_table(),
+            "[default]\n\
+             Mod4+Shift+q                      kill\n\
+             Mod4+Ctrl+l   --release --locked  exec swaylock\n\
+             Mod4+plus                         exec 'a | b'\n\
+             \n\
+             [resize]\n\
+             Escape  mode default\n\
+             \n\
+             [empty]\n"
+        );
+    }
+}
+
+mod lint {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<LintKind> {
+        let tree = fixture_tree();
+        ParsedConfig::parse(text).lint(Some(&tree)).into_iter().map(|lint| lint.kind).collect()
+    }
+
+    #[test]
+    fn clean_config() {
+        let config = "set $mod Mod4\n\
+                      bindsym $mod+r mode resize\n\
+                      bindsym --release $mod+r exec $HOME/bin/x\n\
+                      mode resize {\n\
+                      bindsym Escape mode default\n\
+                      }\n\
+                      for_window [app_id=\"kitty\"] floating enable\n";
+        assert_eq!(kinds(config), []);
+    }
+
+    #[test]
+    fn reports_problems() {
+        let config = "bindsym Mod4+q kill\n\
+                      bindsym mod4+Q exec foo\n\
+                      bindsym Mod4+r mode rezise\n\
+                      bindsym Mod4+w workspace $ws1\n\
+                      bindsym Mod4+x frobnicate\n\
+                      mode orphan {\n\
+                      bindsym Escape mode default\n\
+                      }\n\
+                      for_window [app_id=\"nothing\"] floating enable\n\
+                      gaps inner $gap\n";
+        let lints = ParsedConfig::parse(config).lint(Some(&fixture_tree()));
+        let kinds: Vec<_> = lints.iter().map(|lint| lint.kind.clone()).collect();
+        assert!(matches!(&kinds[0], LintKind::DuplicateBinding { first, .. } if first.line == 1));
+        assert_eq!(kinds[1], LintKind::UnknownMode("rezise".to_string()));
+        assert_eq!(kinds[2], LintKind::UndefinedVariable("$ws1".to_string()));
+        assert!(matches!(&kinds[3], LintKind::InvalidCommand { command, .. } if command == "frobnicate"));
+        assert!(matches!(&kinds[4], LintKind::UnmatchedForWindow(_)));
+        assert_eq!(kinds[5], LintKind::UnreachableMode("orphan".to_string()));
+        assert_eq!(kinds[6], LintKind::UndefinedVariable("$gap".to_string()));
+        assert_eq!(kinds.len(), 7);
+        assert_eq!(lints[1].to_string(), "line 3: error: mode rezise is not defined");
+        assert_eq!(lints[3].severity(), Severity::Warning);
+        assert_eq!(lints[4].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn default_resize_mode() {
+        // From the config sway installs
+        let config = r#"
+set $mod Mod4
+set $left h
+set $down j
+set $up k
+set $right l
+mode "resize" {
+    # left will shrink the containers width
+    # right will grow the containers width
+    # up will shrink the containers height
+    # down will grow the containers height
+    bindsym $left resize shrink width 10px
+    bindsym $down resize grow height 10px
+    bindsym $up resize shrink height 10px
+    bindsym $right resize grow width 10px
+
+    # Ditto, with arrow keys
+    bindsym Left resize shrink width 10px
+    bindsym Down resize grow height 10px
+    bindsym Up resize shrink height 10px
+    bindsym Right resize grow width 10px
+
+    # Return to default mode
+    bindsym Return mode "default"
+    bindsym Escape mode "default"
+}
+bindsym $mod+r mode "resize"
+"#;
+        let lints = ParsedConfig::parse(config).lint(None);
+        let errors: Vec<String> = lints
+            .iter()
+            .filter(|lint| lint.severity() == Severity::Error)
+            .map(|lint| lint.to_string())
+            .collect();
+        assert_eq!(errors, Vec::<String>::new());
+        assert_eq!(lints, []);
+    }
+}
+
+mod swaybar {
+    use super::*;
+
+    #[test]
+    fn writes_stream() {
+        let header = Header { click_events: true, stop_signal: Some(10), ..Header::default() };
+        let mut writer = StatusWriter::new(Vec::new(), &header).unwrap();
+        writer.write(&[Block::new("a")]).unwrap();
+        let block = Block {
+            color: Some(Color { r: 0xff, g: 0, b: 0, a: 0xff }),
+            min_width: Some(MinWidth::Text("100%".to_string())),
+            align: Some(Align::Center),
+            markup: Some(Markup::Pango),
+            border_top: Some(2),
+            ..Block::new("b")
+        };
+        writer.write(&[block]).unwrap();
+        let out = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            out,
+            "{\"version\":1,\"click_events\":true,\"stop_signal\":10}\n[\n\
+             [{\"full_text\":\"a\"}]\n\
+             ,[{\"full_text\":\"b\",\"color\":\"#ff0000ff\",\"border_top\":2,\"min_width\":\"100%\",\"align\":\"center\",\"markup\":\"pango\"}]\n"
+        );
+    }
+
+    #[test]
+    fn reads_clicks() {
+        let input = "[\n\
+                     {\"name\":\"clock\",\"instance\":null,\"x\":10,\"y\":5,\"button\":1,\"event\":272,\
+                     \"relative_x\":3,\"relative_y\":4,\"width\":50,\"height\":20,\"scale\":1.0}\n\
+                     ,{\"name\":\"vol\",\"instance\":\"a\",\"x\":1,\"y\":2,\"button\":4,\"event\":768,\"modifiers\":[\"Shift\"]}\n";
+        let events: Vec<ClickEvent> = ClickEvents::new(input.as_bytes()).map(|e| e.unwrap()).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name.as_deref(), Some("clock"));
+        assert_eq!(events[0].event, Some(272));
+        assert_eq!(events[0].width, 50);
+        assert_eq!(events[1].modifiers, ["Shift"]);
+
+        // i3bar sends no event code
+        let i3bar = "[\n\
+                     {\"name\":\"clock\",\"instance\":null,\"button\":3,\"modifiers\":[],\"x\":1850,\"y\":1065,                     \"relative_x\":20,\"relative_y\":8,\"output_x\":1850,\"output_y\":1065,\"width\":60,\"height\":22}\n";
+        let click = ClickEvents::new(i3bar.as_bytes()).next().unwrap().unwrap();
+        assert_eq!((click.button, click.event, click.scale), (3, None, None));
+        assert!(ClickEvents::new("[\n{oops}\n".as_bytes()).next().unwrap().is_err());
+    }
+}
+
+mod event {
+    use super::*;
+
+    #[test]
+    fn decodes_events() {
+        let event = Event::from_message(
+            0x8000_0014,
+            br#"{"id": "bar-0", "visible_by_modifier": true}"#.to_vec(),
+        ).unwrap();
+        assert_eq!(event.event_type(), EventType::BarStateUpdate);
+
+        let body = serde_json::json!({"change": "focus", "container": fixture_tree().nodes[1]});
+        let event = Event::from_message(0x8000_0003, serde_json::to_vec(&body).unwrap()).unwrap();
+        assert!(matches!(event, Event::Window(WindowEvent { change: WindowChange::Focus, .. })));
+
+        assert!(Event::from_message(0x8000_0042, b"{}".to_vec()).is_err());
+        assert_eq!(serde_json::to_string(&[EventType::BarStateUpdate]).unwrap(), r#"["bar_state_update"]"#);
+    }
+
+    #[test]
+    fn ends_when_closed() {
+        let mut stream = scripted(vec![serde_json::json!({"success": true})]).subscribe(&[EventType::Window]).unwrap();
+        assert!(stream.next().is_none());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn buffers_partial_messages() {
+        use crate::event::take_message;
+        let mut wire = Vec::new();
+        for (code, body) in [(0x8000_0007u32, &b"{\"first\":true}"[..]), (0x8000_0002, b"{}")] {
+            wire.extend_from_slice(b"i3-ipc");
+            wire.extend_from_slice(&(body.len() as u32).to_ne_bytes());
+            wire.extend_from_slice(&code.to_ne_bytes());
+            wire.extend_from_slice(body);
+        }
+        // As if a read timed out after part of the header, then inside the body
+        let mut buffer = wire[..10].to_vec();
+        assert_eq!(take_message(&mut buffer), None);
+        buffer.extend_from_slice(&wire[10..20]);
+        assert_eq!(take_message(&mut buffer), None);
+        buffer.extend_from_slice(&wire[20..]);
+        assert_eq!(take_message(&mut buffer), Some((0x8000_0007, b"{\"first\":true}".to_vec())));
+        assert_eq!(take_message(&mut buffer), Some((0x8000_0002, b"{}".to_vec())));
+        assert!(buffer.is_empty());
+    }
+}
+
+mod bar {
+    use super::*;
+
+    fn bar(mode: &str) -> Bar {
+        serde_json::from_value(serde_json::json!({
+            "id": "bar-0",
+            "mode": mode,
+            "hidden_state": "hide",
+            "position": "bottom",
+            "status_command": null,
+            "font": "monospace",
+            "workspace_buttons": true,
+            "binding_mode_indicator": true,
+            "colors": {}
+        })).unwrap()
+    }
+
+    fn state(id: &str, visible_by_modifier: bool) -> Event {
+        Event::BarStateUpdate(BarStateUpdateEvent { id: id.to_string(), visible_by_modifier })
+    }
+
+    #[test]
+    fn follows_modifier() {
+        let mut watcher = BarWatcher::detached(bar("hide"));
+        assert!(!watcher.visible());
+        assert_eq!(watcher.apply(&state("bar-1", true)), None);
+        assert_eq!(watcher.apply(&state("bar-0", true)), Some(BarChange::Visibility(true)));
+        assert!(watcher.visible_by_modifier());
+        assert_eq!(watcher.apply(&state("bar-0", true)), None);
+        assert_eq!(watcher.apply(&state("bar-0", false)), Some(BarChange::Visibility(false)));
+    }
+
+    #[test]
+    fn follows_config() {
+        let mut watcher = BarWatcher::detached(bar("hide"));
+        assert_eq!(watcher.apply(&Event::BarconfigUpdate(bar("hide"))), None);
+        assert_eq!(watcher.apply(&Event::BarconfigUpdate(bar("dock"))), Some(BarChange::Config));
+        assert!(watcher.visible());
+        assert_eq!(watcher.apply(&state("bar-0", true)), None);
+        assert!(watcher.next().is_none());
+    }
+
+    #[test]
+    fn ends_when_closed() {
+        let config = serde_json::to_value(bar("dock")).unwrap();
+        let socket = scripted(vec![config, serde_json::json!({"success": true})]);
+        let mut watcher = BarWatcher::with_socket(socket, "bar-0").unwrap();
+        assert!(watcher.next().is_none());
+        assert!(watcher.next().is_none());
+    }
+}
+
+mod cache {
+    use super::*;
+
+    fn workspace(name: &str, output: &str, focused: bool) -> Workspace {
+        serde_json::from_value(serde_json::json!({
+            "num": name.parse::<i64>().unwrap_or(-1),
+            "name": name,
+            "visible": true,
+            "focused": focused,
+            "urgent": false,
+            "output": output,
+            "rect": {"x": 0, "y": 0, "width": 0, "height": 0}
+        })).unwrap()
+    }
+
+    fn cache() -> StateCache {
+        let workspaces = vec![workspace("1", "eDP-1", true), workspace("2", "HDMI-A-1", false)];
+        StateCache::from_parts(fixture_tree(), workspaces, Vec::new(), "default".to_string(), Vec::new())
+    }
+
+    fn window_event(change: WindowChange, node: &Node) -> Event {
+        Event::Window(WindowEvent { change, container: Box::new(node.clone()) })
+    }
+
+    #[test]
+    fn applies_window_changes() {
+        let mut cache = cache();
+        assert_eq!(cache.marks(), ["term"]);
+
+        let mut firefox = cache.tree().find(10).unwrap().clone();
+        firefox.name = Some("New Tab".to_string());
+        firefox.marks = vec!["web".to_string()];
+        assert!(cache.apply(&window_event(WindowChange::Title, &firefox)));
+        assert_eq!(cache.tree().find(10).unwrap().name.as_deref(), Some("New Tab"));
+        assert_eq!(cache.marks(), ["web", "term"]);
+
+        let mpv = cache.tree().find(20).unwrap().clone();
+        assert!(cache.apply(&window_event(WindowChange::Focus, &mpv)));
+        assert_eq!(cache.tree().focused().unwrap().id, 20);
+        assert_eq!(cache.tree().focus[0], 6);
+        assert!(!cache.tree().find(13).unwrap().focused);
+        assert!(!cache.is_stale());
+    }
+
+    #[test]
+    fn shows_focused_tab() {
+        let mut tree = fixture_tree();
+        let split = &mut tree.nodes[1].nodes[0].nodes[1];
+        assert_eq!(split.id, 11);
+        split.layout = NodeLayout::Tabbed;
+        split.nodes[0].visible = Some(false);
+        let workspaces = vec![workspace("1", "eDP-1", true), workspace("2", "HDMI-A-1", false)];
+        let mut cache = StateCache::from_parts(tree, workspaces, Vec::new(), "default".to_string(), Vec::new());
+
+        let kitty = cache.tree().find(12).unwrap().clone();
+        assert!(cache.apply(&window_event(WindowChange::Focus, &kitty)));
+        assert_eq!(cache.tree().find(12).unwrap().visible, Some(true));
+        assert_eq!(cache.tree().find(13).unwrap().visible, Some(false));
+        assert_eq!(cache.tree().find(10).unwrap().visible, Some(true));
+        assert!(!cache.is_stale());
+
+        // Fullscreen changes what shows in ways the event does not say
+        assert!(!cache.apply(&window_event(WindowChange::FullscreenMode, &kitty)));
+        assert!(cache.is_stale());
+    }
+
+    #[test]
+    fn focus_on_hidden_workspace_is_stale() {
+        let mut hidden = workspace("2", "HDMI-A-1", false);
+        hidden.visible = false;
+        let workspaces = vec![workspace("1", "eDP-1", true), hidden];
+        let mut cache = StateCache::from_parts(fixture_tree(), workspaces, Vec::new(), "default".to_string(), Vec::new());
+        let mpv = cache.tree().find(20).unwrap().clone();
+        assert!(!cache.apply(&window_event(WindowChange::Focus, &mpv)));
+        assert!(cache.is_stale());
+    }
+
+    #[test]
+    fn applies_workspace_focus() {
+        let mut cache = cache();
+        let mut ws2 = cache.tree().find(7).unwrap().clone();
+        ws2.nodes[0].focused = true;
+        let event = Event::Workspace(WorkspaceEvent {
+            change: WorkspaceChange::Focus,
+            current: Some(Box::new(ws2)),
+            old: None,
+        });
+        assert!(cache.apply(&event));
+        assert_eq!(cache.tree().focused().unwrap().id, 20);
+        assert!(cache.workspaces()[1].focused);
+        assert!(!cache.workspaces()[0].focused);
+        assert!(cache.workspaces()[0].visible);
+    }
+
+    #[test]
+    fn marks_stale() {
+        let mut cache = cache();
+        let new = cache.tree().find(12).unwrap().clone();
+        assert!(!cache.apply(&window_event(WindowChange::New, &new)));
+        assert!(cache.is_stale());
+        assert!(cache.resync().is_err());
+
+        let mut cache = self::cache();
+        let mut unknown = new;
+        unknown.id = 99;
+        assert!(!cache.apply(&window_event(WindowChange::Title, &unknown)));
+        assert!(cache.apply(&Event::Mode(ModeEvent { change: "resize".to_string(), pango_markup: false })));
+        assert_eq!(cache.binding_mode(), "resize");
+    }
+
+    #[test]
+    fn workspaces_from_tree() {
+        // Used after refetching only the tree
+        let derived = crate::cache::workspaces(&fixture_tree());
+        let expected = [workspace("1", "eDP-1", true), workspace("2", "HDMI-A-1", false)];
+        let summary = |ws: &Workspace| (ws.num, ws.name.clone(), ws.output.clone(), ws.focused, ws.visible);
+        assert_eq!(derived.iter().map(summary).collect::<Vec<_>>(), expected.iter().map(summary).collect::<Vec<_>>());
+        assert_eq!(derived[1].rect, fixture_tree().find(7).unwrap().rect);
+    }
+}
+
+mod diff {
+    use super::*;
+
+    #[test]
+    fn reports_changes() {
+        let old = fixture_tree();
+        let mut new = old.clone();
+        {
+            let ws1 = &mut new.nodes[1].nodes[0];
+            ws1.nodes[0].name = Some("New Tab".to_string());
+            ws1.floating_nodes.clear();
+            ws1.nodes[1].layout = NodeLayout::Tabbed;
+            let emacs = ws1.nodes[1].nodes.remove(1);
+            new.nodes[2].nodes[0].nodes.push(emacs);
+        }
+        let diff = old.diff(&new);
+        let changes: Vec<_> = diff.changes.iter().map(|c| (c.id(), c.to_string())).collect();
+        assert_eq!(changes, [
+            (10, "title \"Mozilla Firefox\" -> \"New Tab\"".to_string()),
+            (11, "layout splitv -> tabbed".to_string()),
+            (13, "moved from #11 to #7".to_string()),
+            (13, "moved from workspace \"1\" to \"2\"".to_string()),
+            (14, "removed".to_string()),
+        ]);
+        assert_eq!(diff.for_id(13).count(), 2);
+        assert!(diff.to_string().starts_with("~ con #10 \"firefox\": title"));
+        assert!(old.diff(&old).is_empty());
+    }
+}
+
+mod layout {
+    use super::*;
+    use crate::layout::{arrange, Arrange};
+
+    #[test]
+    fn exports_workspace() {
+        let tree = fixture_tree();
+        let layout = tree.find(5).unwrap().to_layout();
+        assert_eq!(layout.len(), 2);
+        let tiled = &layout[0];
+        assert_eq!(tiled.layout, Some(NodeLayout::Splith));
+        assert_eq!(tiled.nodes[0].percent, Some(0.5));
+        assert_eq!(tiled.nodes[0].swallows[0].app_id.as_deref(), Some("^firefox$"));
+        assert_eq!(tiled.nodes[1].layout, Some(NodeLayout::Splitv));
+        assert_eq!(tiled.nodes[1].nodes[0].marks, ["term"]);
+        assert_eq!(layout[1].node_type, NodeType::FloatingCon);
+        assert_eq!(layout[1].rect.as_ref().unwrap().width, 500);
+
+        let text = layout_to_string(&layout);
+        assert!(text.contains("\"type\": \"floating_con\""));
+        assert_eq!(parse_layout(&text).unwrap(), layout);
+    }
+
+    #[test]
+    fn parses_i3_save_tree() {
+        let text = r#"
+            {"layout": "splitv", "percent": 0.4, "type": "con", "nodes": [
+                {"border": "pixel", "current_border_width": 2, "floating": "auto_off",
+                 "name": "vim", "percent": 1, "type": "con",
+                 "swallows": [{"class": "^URxvt$", "instance": "^urxvt$"}]}
+            ]}
+            {"type": "con", "swallows": [{"app_id": "^firefox$"}]}
+        "#;
+        let layout = parse_layout(text).unwrap();
+        assert_eq!(layout.len(), 2);
+        assert_eq!(layout[0].leaves().len(), 1);
+        assert_eq!(layout[0].nodes[0].floating, Some(NodeFloating::AutoOff));
+        assert_eq!(layout[0].nodes[0].swallows[0].criteria().unwrap().to_string(), r#"[class="^URxvt$" instance="^urxvt$"]"#);
+    }
+
+    #[test]
+    fn claims_distinct_windows() {
+        let tree = fixture_tree();
+        let layout = parse_layout(r#"
+            {"type": "con", "swallows": [{"app_id": "^kitty$"}]}
+            {"type": "con", "swallows": [{"app_id": "^kitty$"}]}
+            {"type": "con", "swallows": [{"app_id": "^(emacs|mpv)$"}]}
+        "#).unwrap();
+        let claims = claim_windows(&tree, &layout, &[13].into()).unwrap();
+        assert_eq!(claims, [Some(12), None, Some(20)]);
+    }
+
+    /// Records chains and answers parent lookups from a script
+    struct Recorder {
+        chains: Vec<String>,
+        parents: Vec<(u64, (u64, NodeType))>,
+    }
+
+    impl Arrange for Recorder {
+        fn run(&mut self, chain: &Chain) -> Result<()> {
+            self.chains.push(chain.to_string());
+            Ok(())
+        }
+
+        fn parent(&mut self, id: u64) -> Result<Option<(u64, NodeType)>> {
+            Ok(self.parents.iter().find(|(child, _)| *child == id).map(|(_, parent)| parent.clone()))
+        }
+    }
+
+    #[test]
+    fn arranges_nested_layout() {
+        let layout = parse_layout(r#"
+            {"type": "con", "layout": "splith", "nodes": [
+                {"type": "con", "percent": 0.6, "border": "pixel", "current_border_width": 2,
+                 "swallows": [{"app_id": "^firefox$"}]},
+                {"type": "con", "percent": 0.4, "layout": "splitv", "marks": ["side"], "nodes": [
+                    {"type": "con", "percent": 0.3, "swallows": [{"app_id": "^kitty$"}]},
+                    {"type": "con", "percent": 0.7, "swallows": [{"app_id": "^mpv$"}]}
+                ]}
+            ]}
+        "#).unwrap();
+        let mut recorder = Recorder {
+            chains: Vec::new(),
+            parents: vec![(2, (102, NodeType::Con)), (1, (101, NodeType::Con))],
+        };
+        let top = arrange(&mut recorder, &layout[0], &mut [Some(1), Some(2), Some(3)].into_iter()).unwrap();
+        assert_eq!(top, Some(101));
+        assert_eq!(recorder.chains, [
+            "[con_id=1] border pixel 2",
+            "[con_id=2] focus, split vertical, layout splitv",
+            "[con_id=102] mark --add _ipc_layout_102",
+            "[con_id=3] move container to mark _ipc_layout_102",
+            "[con_id=2] resize set height 30 ppt",
+            "[con_id=3] resize set height 70 ppt",
+            "[con_id=102] mark --add side",
+            "[con_id=102] unmark _ipc_layout_102",
+            "[con_id=1] focus, split horizontal, layout splith",
+            "[con_id=101] mark --add _ipc_layout_101",
+            "[con_id=102] move container to mark _ipc_layout_101",
+            "[con_id=1] resize set width 60 ppt",
+            "[con_id=102] resize set width 40 ppt",
+            "[con_id=101] unmark _ipc_layout_101",
+        ]);
+    }
+}
+
+mod render {
+    use super::*;
+
+    #[test]
+    fn representation() {
+        let tree = fixture_tree();
+        assert_eq!(tree.find(5).unwrap().layout_representation(), "H[firefox V[kitty emacs]]");
+        let mut split = tree.find(11).unwrap().clone();
+        split.representation = Some("V[a b]".to_string());
+        assert_eq!(split.layout_representation(), "V[a b]");
+    }
+
+    #[test]
+    fn text() {
+        let text = fixture_tree().find(4).unwrap().to_text();
+        assert_eq!(
+            text,
+            "output #4 eDP-1\n\
+             \x20 workspace #5 1 H[firefox V[kitty emacs]]\n\
+             \x20   con #10 firefox \"Mozilla Firefox\"\n\
+             \x20   con #11 V[kitty emacs]\n\
+             \x20     con #12 kitty \"kitty\" [term]\n\
+             \x20     con #13 emacs \"emacs\" *\n\
+             \x20   floating_con #14 pavucontrol \"Volume Control\"\n"
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let dot = fixture_tree().to_dot();
+        assert!(dot.starts_with("digraph tree {"));
+        assert!(dot.contains("n5 [label=\"workspace #5\\n1\\nH[firefox V[kitty emacs]]\", fillcolor=\"palegreen\"];"));
+        assert!(dot.contains("n12 [label=\"con #12\\nkitty\\nmarks: term\", fillcolor=\"white\"];"));
+        assert!(dot.contains("fillcolor=\"white\", penwidth=3"));
+        assert!(dot.contains("n5 -> n14 [style=dashed];"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
+
+mod neighbour {
+    use super::*;
+
+    fn neighbour(id: u64, direction: Direction, options: NeighbourOptions) -> Option<u64> {
+        fixture_tree().neighbour(id, direction, &options).map(|node| node.id)
+    }
+
+    #[test]
+    fn within_output() {
+        let tiled = NeighbourOptions { floating: false, ..NeighbourOptions::default() };
+        assert_eq!(neighbour(13, Direction::Up, NeighbourOptions::default()), Some(12));
+        assert_eq!(neighbour(10, Direction::Right, tiled), Some(12));
+        assert_eq!(neighbour(10, Direction::Right, NeighbourOptions::default()), Some(14));
+        assert_eq!(neighbour(12, Direction::Left, tiled), Some(10));
+        assert_eq!(neighbour(10, Direction::Left, NeighbourOptions::default()), None);
+    }
+
+    #[test]
+    fn across_outputs() {
+        let local = NeighbourOptions { cross_output: false, ..NeighbourOptions::default() };
+        assert_eq!(neighbour(12, Direction::Right, NeighbourOptions::default()), Some(20));
+        assert_eq!(neighbour(20, Direction::Left, NeighbourOptions::default()), Some(13));
+        assert_eq!(neighbour(12, Direction::Right, local), None);
+    }
+
+    #[test]
+    fn wraps() {
+        let output = NeighbourOptions { wrap: Wrap::Output, cross_output: false, ..NeighbourOptions::default() };
+        let global = NeighbourOptions { wrap: Wrap::Global, ..NeighbourOptions::default() };
+        assert_eq!(neighbour(12, Direction::Right, output), Some(10));
+        assert_eq!(neighbour(20, Direction::Right, global), Some(10));
+        assert_eq!(neighbour(20, Direction::Up, output), None);
+    }
+}
+
+mod placement {
+    use super::*;
+
+    fn geometry(x: i64, y: i64, width: i64, height: i64) -> Geometry {
+        Geometry { x, y, width, height }
+    }
+
+    #[test]
+    fn center_and_corners() {
+        let area = geometry(0, 30, 1920, 1050);
+        let window = geometry(100, 100, 500, 400);
+        let options = PlacementOptions { margin: 10, ..PlacementOptions::default() };
+        assert_eq!(place(&area, &window, Placement::Center, &options), geometry(710, 355, 500, 400));
+        assert_eq!(place(&area, &window, Placement::Corner(Corner::TopLeft), &options), geometry(10, 40, 500, 400));
+        assert_eq!(
+            place(&area, &window, Placement::Corner(Corner::BottomRight), &options),
+            geometry(1410, 670, 500, 400),
+        );
+    }
+
+    #[test]
+    fn thirds_and_grid() {
+        let area = geometry(0, 0, 1920, 1080);
+        let window = geometry(0, 0, 100, 100);
+        let options = PlacementOptions::default();
+        assert_eq!(place(&area, &window, Placement::Third(2), &options), geometry(1280, 0, 640, 1080));
+        let cell = Placement::Grid { columns: 2, rows: 2, column: 1, row: 1 };
+        let gaps = PlacementOptions { margin: 20, ..options };
+        assert_eq!(place(&area, &window, cell, &gaps), geometry(970, 550, 930, 510));
+    }
+
+    #[test]
+    fn inside_clamps() {
+        let area = geometry(0, 0, 1920, 1080);
+        let window = geometry(1800, -50, 500, 400);
+        let options = PlacementOptions::default();
+        assert_eq!(place(&area, &window, Placement::Inside, &options), geometry(1420, 0, 500, 400));
+    }
+
+    #[test]
+    fn scaled_output() {
+        let area = geometry(0, 0, 1280, 720);
+        let window = geometry(0, 0, 100, 100);
+        // 1.5 scale: logical pixels must be even to be whole physical pixels
+        let options = PlacementOptions { scale: 1.5, size: Some((600, 400)), ..PlacementOptions::default() };
+        let placed = place(&area, &window, Placement::Center, &options);
+        assert_eq!(placed, geometry(340, 160, 600, 400));
+        // The size is logical too and only snapped, never scaled
+        let odd = PlacementOptions { size: Some((601, 401)), ..options.clone() };
+        assert_eq!(place(&area, &window, Placement::Center, &odd), geometry(340, 160, 600, 400));
+
+        // Physical pixels count from the output's origin, here an odd one
+        let area = geometry(1365, 0, 1280, 720);
+        let offset = PlacementOptions { origin: (1365, 0), ..options.clone() };
+        let placed = place(&area, &window, Placement::Center, &offset);
+        assert_eq!(placed, geometry(1705, 160, 600, 400));
+        assert_eq!((placed.x - 1365) % 2, 0);
+        let third = place(&area, &window, Placement::Third(1), &options);
+        assert_eq!(third.x % 2, 0);
+        assert_eq!((third.x + third.width) % 2, 0);
+    }
+
+    #[test]
+    fn from_tree() {
+        let tree = fixture_tree();
+        let placed = tree.placement(14, Placement::Center, &PlacementOptions::default()).unwrap();
+        assert_eq!(placed, geometry(710, 340, 500, 400));
+        assert_eq!(
+            placed.commands(14).to_string(),
+            "[con_id=14] floating enable, resize set width 500 px height 400 px, move absolute position 710 px 340 px",
+        );
+        assert!(tree.placement(99, Placement::Center, &PlacementOptions::default()).is_none());
+    }
+}
+
+mod wait {
+    use super::*;
+    use std::time::Duration;
+
+    fn cache() -> StateCache {
+        StateCache::from_parts(fixture_tree(), Vec::new(), Vec::new(), "default".to_string(), Vec::new())
+    }
+
+    #[test]
+    fn already_present() {
+        let mut cache = cache();
+        let criteria = Criteria::parse("[app_id=kitty]").unwrap();
+        assert_eq!(cache.wait_for_window(&criteria, Duration::from_secs(1)).unwrap().id, 12);
+        let marked = cache.wait_until(Duration::from_secs(1), |node| node.marks.iter().any(|m| m == "term")).unwrap();
+        assert_eq!(marked.id, 12);
+        assert_eq!(cache.wait_for(Duration::ZERO, |cache| Some(cache.binding_mode().to_string())).unwrap(), "default");
+    }
+
+    #[test]
+    fn matches_event_containers() {
+        use crate::wait::matching_window;
+        let window = |change: &str| {
+            let body = serde_json::json!({"change": change, "container": fixture_tree().find(12).unwrap()});
+            Event::from_message(0x8000_0003, serde_json::to_vec(&body).unwrap()).unwrap()
+        };
+        // The workspace is not in the payload, so only the rest is checked there
+        let criteria = Criteria::parse("[app_id=kitty workspace=^3$ floating]").unwrap();
+        let local = criteria.local();
+        assert_eq!(local.to_string(), r#"[app_id="kitty"]"#);
+        assert_eq!(matching_window(&window("new"), &local).map(|node| node.id), Some(12));
+        assert_eq!(matching_window(&window("title"), &local).map(|node| node.id), Some(12));
+        assert!(matching_window(&window("close"), &local).is_none());
+        assert!(matching_window(&window("new"), &Criteria::parse("[app_id=firefox]").unwrap()).is_none());
+    }
+
+    #[test]
+    fn times_out() {
+        let mut cache = cache();
+        let criteria = Criteria::parse("[app_id=nothing]").unwrap();
+        match cache.wait_for_window(&criteria, Duration::ZERO) {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::TimedOut),
+            other => panic!("expected a timeout, got {:?}", other.map(|node| node.id)),
+        }
+        // Without an event stream there is nothing to wait on
+        match cache.wait_until(Duration::from_secs(1), |node| node.id == 99) {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotConnected),
+            other => panic!("expected no connection, got {:?}", other.map(|node| node.id)),
+        }
+    }
+}
+
+mod launch {
+    use super::*;
+    use crate::launch::{parse_stat, process_tree};
+
+    #[test]
+    fn commands() {
+        let notes = launch("foot -a notes").on_workspace("3").floating(true).size(800, 600).mark("notes");
+        assert_eq!(
+            notes.commands(42).unwrap().to_string(),
+            "[con_id=42] floating enable, move container to workspace 3, resize set width 800 px height 600 px, mark --add notes",
+        );
+        // Placement floats the window and takes care of the size itself
+        let placed = launch("pavucontrol").size(800, 600).place(Placement::Center);
+        assert_eq!(placed.commands(7).unwrap().to_string(), "[con_id=7] floating enable");
+        let tiled = placed.clone().floating(false);
+        assert_eq!(tiled.commands(7).unwrap().to_string(), "[con_id=7] floating enable");
+        assert!(launch("true").commands(7).is_none());
+    }
+
+    #[test]
+    fn proc_stat() {
+        assert_eq!(parse_stat("4242 (foot) S 4200 4242 4200 0 -1"), Some((4242, 4200)));
+        assert_eq!(parse_stat("17 (a) b) (c) R 1 17 17 0 -1"), Some((17, 1)));
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[test]
+    fn descendants() {
+        let processes = [(1, 0), (100, 1), (101, 100), (102, 101), (103, 1), (104, 102)];
+        let mut family: Vec<u64> = process_tree(&processes, 100).into_iter().collect();
+        family.sort();
+        assert_eq!(family, [100, 101, 102, 104]);
+        assert_eq!(process_tree(&processes, 999).len(), 1);
+    }
+}
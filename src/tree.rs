@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::mem;
+
+use crate::{Node, NodeType};
+
+/// Arena-backed copy of a `get_tree` reply with parent links and id lookup
+///
+/// Nodes are stored with empty `nodes` and `floating_nodes`; walk the
+/// structure through [`Tree::children`] and [`Tree::floating_children`].
+pub struct Tree {
+    entries: Vec<Entry>,
+    index: HashMap<u64, usize>,
+}
+
+struct Entry {
+    node: Node,
+    parent: Option<usize>,
+    nodes: Vec<usize>,
+    floating_nodes: Vec<usize>,
+    workspace: Option<usize>,
+    output: Option<usize>,
+}
+
+impl Tree {
+    /// Build the arena from a tree, usually the root returned by `get_tree`
+    pub fn new(root: Node) -> Tree {
+        let mut tree = Tree { entries: Vec::new(), index: HashMap::new() };
+        tree.insert(root, None);
+        tree
+    }
+
+    fn insert(&mut self, mut node: Node, parent: Option<usize>) -> usize {
+        let idx = self.entries.len();
+        let inherited = parent.map(|p| &self.entries[p]);
+        let workspace = match node.node_type {
+            NodeType::Workspace => Some(idx),
+            _ => inherited.and_then(|p| p.workspace),
+        };
+        let output = match node.node_type {
+            NodeType::Output => Some(idx),
+            _ => inherited.and_then(|p| p.output),
+        };
+        let nodes = mem::take(&mut node.nodes);
+        let floating_nodes = mem::take(&mut node.floating_nodes);
+        self.index.insert(node.id, idx);
+        self.entries.push(Entry {
+            node,
+            parent,
+            nodes: Vec::new(),
+            floating_nodes: Vec::new(),
+            workspace,
+            output,
+        });
+        let nodes = nodes.into_iter().map(|child| self.insert(child, Some(idx))).collect();
+        let floating_nodes = floating_nodes
+            .into_iter()
+            .map(|child| self.insert(child, Some(idx)))
+            .collect();
+        self.entries[idx].nodes = nodes;
+        self.entries[idx].floating_nodes = floating_nodes;
+        idx
+    }
+
+    fn entry(&self, id: u64) -> Option<&Entry> {
+        self.index.get(&id).map(|&idx| &self.entries[idx])
+    }
+
+    /// Root node
+    pub fn root(&self) -> &Node {
+        &self.entries[0].node
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the tree has no nodes; never true for a tree built by `new`
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All nodes in depth-first order
+    pub fn iter(&self) -> impl Iterator<Item = &Node> {
+        self.entries.iter().map(|entry| &entry.node)
+    }
+
+    /// Node with the given id
+    pub fn get(&self, id: u64) -> Option<&Node> {
+        self.entry(id).map(|entry| &entry.node)
+    }
+
+    /// Whether a node with the given id exists
+    pub fn contains(&self, id: u64) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    /// Parent of the given node
+    pub fn parent(&self, id: u64) -> Option<&Node> {
+        let parent = self.entry(id)?.parent?;
+        Some(&self.entries[parent].node)
+    }
+
+    /// Tiling children of the given node
+    pub fn children(&self, id: u64) -> impl Iterator<Item = &Node> {
+        let children = self.entry(id).map(|entry| entry.nodes.as_slice()).unwrap_or(&[]);
+        children.iter().map(move |&idx| &self.entries[idx].node)
+    }
+
+    /// Floating children of the given node
+    pub fn floating_children(&self, id: u64) -> impl Iterator<Item = &Node> {
+        let children = self.entry(id).map(|entry| entry.floating_nodes.as_slice()).unwrap_or(&[]);
+        children.iter().map(move |&idx| &self.entries[idx].node)
+    }
+
+    /// Other children of the same parent, in the same tiling or floating list
+    pub fn siblings(&self, id: u64) -> impl Iterator<Item = &Node> {
+        let list = self.entry(id).and_then(|entry| entry.parent).map(|parent| {
+            let parent = &self.entries[parent];
+            if parent.floating_nodes.iter().any(|&idx| self.entries[idx].node.id == id) {
+                parent.floating_nodes.as_slice()
+            } else {
+                parent.nodes.as_slice()
+            }
+        });
+        list.unwrap_or(&[])
+            .iter()
+            .map(move |&idx| &self.entries[idx].node)
+            .filter(move |node| node.id != id)
+    }
+
+    /// The given node followed by its ancestors up to the root
+    pub fn path_to_root(&self, id: u64) -> Vec<&Node> {
+        let mut path = Vec::new();
+        let mut current = self.index.get(&id).copied();
+        while let Some(idx) = current {
+            path.push(&self.entries[idx].node);
+            current = self.entries[idx].parent;
+        }
+        path
+    }
+
+    /// Workspace containing the given node, or the node itself if it is one
+    pub fn workspace_of(&self, id: u64) -> Option<&Node> {
+        let workspace = self.entry(id)?.workspace?;
+        Some(&self.entries[workspace].node)
+    }
+
+    /// Output containing the given node, or the node itself if it is one
+    pub fn output_of(&self, id: u64) -> Option<&Node> {
+        let output = self.entry(id)?.output?;
+        Some(&self.entries[output].node)
+    }
+}
+
+impl From<Node> for Tree {
+    fn from(root: Node) -> Tree {
+        Tree::new(root)
+    }
+}
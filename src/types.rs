@@ -3,8 +3,9 @@ extern crate serde;
 extern crate serde_json;
 extern crate serde_repr;
 
-use serde::Deserializer;
-use serde_repr::Deserialize_repr;
+use serde::{Deserializer, Serializer};
+use serde::de::{Error as _, Unexpected};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
 pub enum CommandError {
     ParseError,
@@ -13,7 +14,7 @@ pub enum CommandError {
 
 // Response structs
 // Run Command
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct CommandResult {
     pub success: bool,
     pub parse_error: Option<bool>,
@@ -21,7 +22,7 @@ pub struct CommandResult {
 }
 
 // Get Workspaces
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Workspace {
     pub num: i64,
     pub name: String,
@@ -29,9 +30,12 @@ pub struct Workspace {
     pub focused: bool,
     pub urgent: bool,
     pub output: String,
-    pub rect: Rectangle
+    pub rect: Rectangle,
+    #[cfg(feature = "extra_fields")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Rectangle {
     pub x: u64,
     pub y: u64,
@@ -40,27 +44,36 @@ pub struct Rectangle {
 }
 
 // Get Output
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Output {
     pub name: String,
+    // Not reported by i3
+    #[serde(default)]
     pub make: String,
+    #[serde(default)]
     pub model: String,
+    #[serde(default)]
     pub serial: String,
     pub active: bool,
+    #[serde(default)]
     pub dpms: bool,
     pub primary: bool,
     pub scale: Option<f64>,
     pub subpixel_hinting: Option<SubpixelHinting>,
     pub transform: Option<OutputTransform>,
     pub current_workspace: Option<String>,
+    #[serde(default)]
     pub modes: Vec<OutputMode>,
     pub current_mode: Option<OutputMode>,
-    pub rect: Rectangle
+    pub rect: Rectangle,
+    #[cfg(feature = "extra_fields")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum SubpixelHinting { RGB, BGR, VRGB, VBGR, None, Unknown }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum OutputTransform {
     #[serde(rename = "normal")]
     Normal,
@@ -77,7 +90,7 @@ pub enum OutputTransform {
     #[serde(rename = "flipped-270")]
     FlippedLeft
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct OutputMode {
     pub width: u64,
     pub height: u64,
@@ -85,17 +98,18 @@ pub struct OutputMode {
 }
 
 // Get Tree
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Node {
     pub id: u64,
     pub name: Option<String>,
     #[serde(rename = "type")]
     pub node_type: NodeType,
     pub border: NodeBorder,
-    pub current_border_width: u8,
+    /// `-1` on i3 for containers without a border of their own
+    pub current_border_width: i32,
     pub layout: NodeLayout,
     pub orientation: NodeOrientation,
-    //percent: Option<f64>,
+    pub percent: Option<f64>,
     pub rect: Rectangle,
     pub window_rect: Rectangle,
     pub deco_rect: Rectangle,
@@ -112,9 +126,21 @@ pub struct Node {
     pub pid: Option<u64>,
     pub visible: Option<bool>,
     pub window: Option<u64>,
-    pub window_properties: Option<NodeWindowProperties>
+    pub window_properties: Option<NodeWindowProperties>,
+    #[serde(default)]
+    pub marks: Vec<String>,
+    pub shell: Option<String>,
+    pub inhibit_idle: Option<bool>,
+    pub idle_inhibitors: Option<NodeIdleInhibitors>,
+    pub max_render_time: Option<u64>,
+    // Only reported by i3
+    pub floating: Option<NodeFloating>,
+    pub window_type: Option<String>,
+    #[cfg(feature = "extra_fields")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeType {
     Root,
@@ -122,31 +148,45 @@ pub enum NodeType {
     Workspace,
     Con,
     #[serde(rename = "floating_con")]
-    FloatingCon
+    FloatingCon,
+    Dockarea
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeBorder { Normal, None, Pixel, Csd }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub enum NodeLayout { None, Splith, Splitv, Stacked, Tabbed, Output }
-#[derive(Deserialize, Debug, PartialEq)]
+pub enum NodeLayout { None, Splith, Splitv, Stacked, Tabbed, Output, Dockarea }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum NodeOrientation { Vertical, Horizontal, None }
-#[derive(Deserialize_repr, Debug, PartialEq)]
+#[derive(Deserialize_repr, Serialize_repr, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum NodeFullscreenMode { None = 0, Full = 1, Global = 2 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeFloating { AutoOff, AutoOn, UserOff, UserOn }
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct NodeIdleInhibitors {
+    /// Set with `inhibit_idle`: `focus`, `fullscreen`, `open`, `visible` or `none`
+    pub user: String,
+    /// Requested by the application: `enabled` or `none`
+    pub application: String,
+}
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
 pub struct NodeWindowProperties {
     pub class: String,
     pub instance: String,
-    pub title: String
-//  pub   transient_for: unknown
+    pub title: String,
+    pub transient_for: Option<u64>,
+    // Only reported by i3
+    pub window_role: Option<String>
 }
 
 // Get Bar Config
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Bar {
     pub id: String,
     pub mode: BarMode,
@@ -156,55 +196,72 @@ pub struct Bar {
     pub workspace_buttons: bool,
     pub binding_mode_indicator: bool,
     pub colors: BarColors,
-    pub gaps: BarGaps,
-    pub bar_height: u64,
-    pub status_padding: u64,
-    pub status_edge_padding: u64,
+    pub hidden_state: Option<String>,
+    pub modifier: Option<String>,
+    pub verbose: Option<bool>,
+    pub strip_workspace_numbers: Option<bool>,
+    pub strip_workspace_name: Option<bool>,
+    pub workspace_min_width: Option<u64>,
+    pub tray_padding: Option<u64>,
+    #[serde(default)]
+    pub tray_outputs: Vec<String>,
+    // Only reported by sway
+    pub gaps: Option<BarGaps>,
+    pub bar_height: Option<u64>,
+    pub status_padding: Option<u64>,
+    pub status_edge_padding: Option<u64>,
+    // Only reported by i3
+    pub separator_symbol: Option<String>,
+    #[cfg(feature = "extra_fields")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub enum BarMode { Dock, Hide, Invisible }
-#[derive(Deserialize, Debug, PartialEq)]
+pub enum BarMode { Dock, Hide, Invisible, Overlay }
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum BarPosition { Bottom, Top }
-#[derive(Deserialize, Debug, PartialEq)]
+// i3 only reports colors that were set in the config
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
 pub struct BarColors {
-    pub background: Color,
-    pub statusline: Color,
-    pub separator: Color,
+    pub background: Option<Color>,
+    pub statusline: Option<Color>,
+    pub separator: Option<Color>,
 
-    pub focused_background: Color,
-    pub focused_statusline: Color,
-    pub focused_separator: Color,
+    pub focused_background: Option<Color>,
+    pub focused_statusline: Option<Color>,
+    pub focused_separator: Option<Color>,
 
-    pub focused_workspace_text: Color,
-    pub focused_workspace_bg: Color,
-    pub focused_workspace_border: Color,
+    pub focused_workspace_text: Option<Color>,
+    pub focused_workspace_bg: Option<Color>,
+    pub focused_workspace_border: Option<Color>,
 
-    pub active_workspace_text: Color,
-    pub active_workspace_bg: Color,
-    pub active_workspace_border: Color,
+    pub active_workspace_text: Option<Color>,
+    pub active_workspace_bg: Option<Color>,
+    pub active_workspace_border: Option<Color>,
 
-    pub inactive_workspace_text: Color,
-    pub inactive_workspace_bg: Color,
-    pub inactive_workspace_border: Color,
+    pub inactive_workspace_text: Option<Color>,
+    pub inactive_workspace_bg: Option<Color>,
+    pub inactive_workspace_border: Option<Color>,
 
-    pub urgent_workspace_text: Color,
-    pub urgent_workspace_bg: Color,
-    pub urgent_workspace_border: Color,
+    pub urgent_workspace_text: Option<Color>,
+    pub urgent_workspace_bg: Option<Color>,
+    pub urgent_workspace_border: Option<Color>,
 
-    pub binding_mode_text: Color,
-    pub binding_mode_bg: Color,
-    pub binding_mode_border: Color,
+    pub binding_mode_text: Option<Color>,
+    pub binding_mode_bg: Option<Color>,
+    pub binding_mode_border: Option<Color>,
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
     pub a: u8
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct BarGaps {
     pub top: u64,
     pub right: u64,
@@ -213,28 +270,29 @@ pub struct BarGaps {
 }
 
 // Get Version
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Version {
     pub major: u64,
     pub minor: u64,
     pub patch: u64,
     pub human_readable: String,
-    pub loaded_config_file_name: String
+    pub loaded_config_file_name: String,
+    pub variant: Option<String>
 }
 
 //Get Config
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Config {
     pub config: String
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct BindingState {
     pub name: String
 }
 
 // Get Inputs
-#[derive(Deserialize, Debug, Default, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 #[serde(default)]
 pub struct Input {
     pub identifier: String,
@@ -249,9 +307,12 @@ pub struct Input {
     pub layouts: Option<Vec<String>>,
     #[serde(rename = "xkb_active_layout_index")]
     pub layout_index: u64,
-    pub libinput: InputSettings
+    pub libinput: InputSettings,
+    #[cfg(feature = "extra_fields")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>
 }
-#[derive(Deserialize, Debug, Default, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 #[serde(default)]
 pub struct InputSettings {
     pub send_events: Option<InputSendEvents>,
@@ -270,7 +331,7 @@ pub struct InputSettings {
     pub dwt: Option<Toggle>,
     pub calibration_matrix: Option<[f32; 6]>
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum InputSendEvents {
     Enabled,
@@ -278,22 +339,22 @@ pub enum InputSendEvents {
     #[serde(rename = "disabled_on_external_mouse")]
     DisabledOnExternalMouse
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum TapButtonMap {
     LMR,
     LRM
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Toggle {
     Enabled,
     Disabled,
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum InputAccelProfile { None, Flat, Adaptive }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum InputClickMethod {
     None,
@@ -301,7 +362,7 @@ pub enum InputClickMethod {
     ButtonAreas,
     ClickFinger
 }
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum InputScrollMethod {
     None,
@@ -313,7 +374,7 @@ pub enum InputScrollMethod {
 }
 
 // Get Seats
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Seat  {
     pub name: String,
     pub capabilities: u64,
@@ -327,15 +388,29 @@ impl<'de> serde::de::Deserialize<'de> for Color {
     where
         D: Deserializer<'de>,
     {
-        let s: &str = serde::de::Deserialize::deserialize(deserializer)?;
-        debug_assert!(s.starts_with("#"), "Invalid colorcode format");
-        // Read
+        let s: String = serde::de::Deserialize::deserialize(deserializer)?;
+        let invalid = || D::Error::invalid_value(Unexpected::Str(&s), &"#rrggbb or #rrggbbaa");
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        // i3 omits the alpha channel
         Ok(Color{
-            r: u8::from_str_radix(&s[1..2], 16).unwrap(),
-            g: u8::from_str_radix(&s[3..4], 16).unwrap(),
-            b: u8::from_str_radix(&s[5..6], 16).unwrap(),
-            a: u8::from_str_radix(&s[7..8], 16).unwrap()
+            r: byte(0)?,
+            g: byte(2)?,
+            b: byte(4)?,
+            a: if hex.len() == 8 { byte(6)? } else { 0xff }
         })
     }
 }
 
+impl serde::ser::Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s = format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a);
+        serializer.serialize_str(&s)
+    }
+}
@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::Version;
+
+/// Pre-release stage of a build, parsed from `human_readable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Development build, e.g. `1.10-dev-9fd1b2c4`
+    Dev,
+    /// Release candidate, e.g. `1.9-rc2`
+    Rc(u64),
+    /// Tagged release
+    Release,
+}
+
+/// Window manager on the other end of the socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compositor {
+    Sway,
+    I3,
+}
+
+/// Protocol features that are not available on every compositor version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `GET_BINDING_STATE` message
+    GetBindingState,
+    /// `GET_CONFIG` message
+    GetConfig,
+    /// `GET_BINDING_MODES` message
+    GetBindingModes,
+    /// `GET_INPUTS` message and `input` events
+    Inputs,
+    /// `GET_SEATS` message
+    Seats,
+    /// `SEND_TICK` message and `tick` events
+    Tick,
+    /// `SYNC` message
+    Sync,
+    /// `shutdown` events
+    ShutdownEvent,
+    /// `bar_state_update` events
+    BarStateUpdate,
+    /// `max_render_time` on outputs
+    MaxRenderTime,
+}
+
+impl Feature {
+    /// Minimum sway version providing this feature, or `None` if sway lacks it
+    pub fn min_sway(self) -> Option<(u64, u64, u64)> {
+        match self {
+            Feature::GetBindingState => Some((1, 5, 0)),
+            Feature::GetConfig
+            | Feature::GetBindingModes
+            | Feature::Inputs
+            | Feature::Seats
+            | Feature::Tick
+            | Feature::ShutdownEvent
+            | Feature::BarStateUpdate => Some((1, 0, 0)),
+            Feature::MaxRenderTime => Some((1, 4, 0)),
+            Feature::Sync => None,
+        }
+    }
+
+    /// Minimum version of `compositor` providing this feature, or `None` if it lacks it
+    pub fn min_version(self, compositor: Compositor) -> Option<(u64, u64, u64)> {
+        match compositor {
+            Compositor::Sway => self.min_sway(),
+            Compositor::I3 => self.min_i3(),
+        }
+    }
+
+    /// Minimum i3 version providing this feature, or `None` if i3 lacks it
+    pub fn min_i3(self) -> Option<(u64, u64, u64)> {
+        match self {
+            Feature::GetBindingState => Some((4, 19, 0)),
+            Feature::GetConfig => Some((4, 14, 0)),
+            Feature::GetBindingModes => Some((4, 13, 0)),
+            Feature::Tick => Some((4, 15, 0)),
+            Feature::Sync => Some((4, 16, 0)),
+            Feature::ShutdownEvent => Some((4, 14, 0)),
+            Feature::Inputs
+            | Feature::Seats
+            | Feature::BarStateUpdate
+            | Feature::MaxRenderTime => None,
+        }
+    }
+}
+
+impl Version {
+    /// Compositor that sent this version
+    ///
+    /// Sway sets `variant`; older releases that don't are told apart by the
+    /// major version, since sway is still at 1.x while i3 is at 4.x.
+    pub fn compositor(&self) -> Compositor {
+        match self.variant.as_deref() {
+            Some("i3") => Compositor::I3,
+            Some(_) => Compositor::Sway,
+            None if self.major >= 4 => Compositor::I3,
+            None => Compositor::Sway,
+        }
+    }
+
+    /// Pre-release stage, parsed from `human_readable`
+    pub fn stage(&self) -> Stage {
+        parse_suffix(&self.human_readable).0
+    }
+
+    /// Git commit the compositor was built from, if `human_readable` names one
+    pub fn commit(&self) -> Option<&str> {
+        parse_suffix(&self.human_readable).1
+    }
+
+    /// `(major, minor, patch)` triple
+    pub fn triple(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+
+    /// Whether this version is at least `major.minor.patch`
+    ///
+    /// Pre-releases of the requested version count as not reaching it.
+    pub fn at_least(&self, major: u64, minor: u64, patch: u64) -> bool {
+        (self.triple(), self.stage()) >= ((major, minor, patch), Stage::Release)
+    }
+
+    /// Whether the compositor that sent this version supports `feature`
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature.min_version(self.compositor()) {
+            Some((major, minor, patch)) => self.at_least(major, minor, patch),
+            None => false,
+        }
+    }
+}
+
+/// Split `human_readable` into its stage and commit hash
+///
+/// Handles `1.8.1`, `1.9-rc1`, `1.10-dev-9fd1b2c4 (...)` and git describe
+/// output such as `4.20-105-g1ab1d8f (...)`.
+fn parse_suffix(human_readable: &str) -> (Stage, Option<&str>) {
+    let token = human_readable.split_whitespace().next().unwrap_or("");
+    let mut stage = Stage::Release;
+    let mut commit = None;
+    for part in token.split('-').skip(1) {
+        if part == "dev" {
+            stage = Stage::Dev;
+        } else if let Some(n) = part.strip_prefix("rc").and_then(|n| n.parse().ok()) {
+            stage = Stage::Rc(n);
+        } else if part.parse::<u64>().is_ok() {
+            // Commits since the tag in git describe output
+        } else {
+            let hash = part.strip_prefix('g').unwrap_or(part);
+            if !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                commit = Some(hash);
+            }
+        }
+    }
+    (stage, commit)
+}
+
+/// Error returned when a version string has no leading `major.minor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVersionError(pub String);
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    /// Parse a `human_readable` version string
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_string());
+        let token = s.split_whitespace().next().ok_or_else(err)?;
+        let number = token.split('-').next().unwrap_or("");
+        let mut parts = number.split('.').map(|p| p.parse::<u64>());
+        let major = parts.next().and_then(|p| p.ok()).ok_or_else(err)?;
+        let minor = parts.next().and_then(|p| p.ok()).ok_or_else(err)?;
+        let patch = match parts.next() {
+            Some(p) => p.map_err(|_| err())?,
+            None => 0,
+        };
+        Ok(Version {
+            major,
+            minor,
+            patch,
+            human_readable: s.to_string(),
+            loaded_config_file_name: String::new(),
+            variant: None,
+        })
+    }
+}
+
+impl Eq for Version {}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.triple(), self.stage())
+            .cmp(&(other.triple(), other.stage()))
+            .then_with(|| self.human_readable.cmp(&other.human_readable))
+            .then_with(|| self.variant.cmp(&other.variant))
+            .then_with(|| self.loaded_config_file_name.cmp(&other.loaded_config_file_name))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,109 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Criteria, Event, Node, StateCache, WindowChange, WorkspaceChange};
+
+impl StateCache {
+    /// Apply events until `check` returns something, or fail with `TimedOut`
+    ///
+    /// `check` runs against the current state first, then after every event.
+    /// Create the cache before starting whatever is awaited, so that no event
+    /// is missed in between.
+    pub fn wait_for<T>(&mut self, timeout: Duration, mut check: impl FnMut(&StateCache) -> Option<T>) -> crate::Result<T> {
+        if let Some(found) = check(self) {
+            return Ok(found);
+        }
+        self.watch(timeout, |cache| {
+            cache.next_event()?;
+            Ok(check(cache))
+        })
+    }
+
+    /// Wait until a container satisfies `predicate` and return it
+    ///
+    /// The tree is checked once, then only the containers that window and
+    /// workspace events carry.
+    pub fn wait_until(&mut self, timeout: Duration, mut predicate: impl FnMut(&Node) -> bool) -> crate::Result<Node> {
+        if let Some(node) = self.tree().iter().find(|node| predicate(node)) {
+            return Ok(node.clone());
+        }
+        self.watch(timeout, |cache| {
+            let found = match cache.read_event()? {
+                Event::Window(e) if e.change != WindowChange::Close => e.container.iter().find(|node| predicate(node)).cloned(),
+                Event::Workspace(e) if e.change != WorkspaceChange::Empty => {
+                    e.current.and_then(|current| current.iter().find(|node| predicate(node)).cloned())
+                }
+                _ => None,
+            };
+            Ok(found)
+        })
+    }
+
+    /// Wait until a window matching `criteria` exists and return it
+    ///
+    /// Window events are matched on their container. Only when that matches
+    /// and the criteria also need the rest of the tree, such as `workspace`
+    /// or `floating`, is the tree fetched to check them.
+    pub fn wait_for_window(&mut self, criteria: &Criteria, timeout: Duration) -> crate::Result<Node> {
+        if let Some(&node) = self.tree().matching(criteria).first() {
+            return Ok(node.clone());
+        }
+        let local = criteria.local();
+        self.watch(timeout, |cache| {
+            let event = cache.read_event()?;
+            cache.event_window(&event, criteria, &local)
+        })
+    }
+
+    /// The window of `event` if it matches `criteria`, given `local` as
+    /// `criteria.local()`; the tree is refetched only for the other tokens
+    pub(crate) fn event_window(&mut self, event: &Event, criteria: &Criteria, local: &Criteria) -> crate::Result<Option<Node>> {
+        let Some(window) = matching_window(event, local) else { return Ok(None) };
+        if local.0.len() == criteria.0.len() {
+            return Ok(Some(window.clone()));
+        }
+        if self.is_stale() {
+            self.refresh()?;
+        }
+        let matched = self.tree().matching(criteria).iter().any(|node| node.id == window.id);
+        Ok(matched.then(|| window.clone()))
+    }
+
+    /// Run `step` until it returns something, or fail with `TimedOut`, then
+    /// refetch whatever the events it read left stale
+    pub(crate) fn watch<T>(&mut self, timeout: Duration, mut step: impl FnMut(&mut StateCache) -> crate::Result<Option<T>>) -> crate::Result<T> {
+        let deadline = Instant::now() + timeout;
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(timed_out(timeout));
+            }
+            self.set_read_timeout(Some(remaining))?;
+            match step(self) {
+                Ok(Some(found)) => break Ok(found),
+                Ok(None) => {}
+                Err(crate::Error::IoError(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    break Err(timed_out(timeout));
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        self.set_read_timeout(None)?;
+        if self.is_stale() {
+            self.refresh()?;
+        }
+        result
+    }
+}
+
+/// The container of a window event if it matches `criteria` on its own
+pub(crate) fn matching_window<'a>(event: &'a Event, criteria: &Criteria) -> Option<&'a Node> {
+    match event {
+        Event::Window(e) if e.change != WindowChange::Close => e.container.matching(criteria).first().copied(),
+        _ => None,
+    }
+}
+
+fn timed_out(timeout: Duration) -> crate::Error {
+    io::Error::new(io::ErrorKind::TimedOut, format!("condition not met within {:?}", timeout)).into()
+}